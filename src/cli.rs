@@ -0,0 +1,471 @@
+use std::path::PathBuf;
+
+use crate::computer::ComputerWord;
+
+pub const USAGE: &str = "\
+usage: advent-2019 [--day N] [--part 1|2] [--input PATH] [--jobs N]
+       advent-2019 list
+       advent-2019 verify [--day N] [--answers PATH]
+       advent-2019 bench [--day N] [--input PATH] [--runs N] [--json] [--intcode]
+       advent-2019 debug <program> [inputs...]
+       advent-2019 cfg <program>
+       advent-2019 profile <program> [inputs...]
+       advent-2019 play <program>
+       advent-2019 intcode run <program> [--input N,N,...]
+       advent-2019 intcode convert <program> <output>
+
+Runs every day with its checked-in input unless --day picks one.
+--input replaces the checked-in input; use '-' to read it from stdin.
+--jobs runs up to N days at once, one per CPU by default.
+verify checks each answer against the recorded answers file.
+bench times parsing and each part over --runs runs (10 by default).
+--intcode compares the Intcode engines on day 9's BOOST program instead.
+cfg prints a program's control-flow graph as Graphviz DOT.
+profile runs a program and prints its listing with how often each line ran.
+play runs an ASCII program in the terminal, sending it each line typed.
+intcode run runs any program with the given input and prints its output;
+a program of '-' is read from stdin, and may be text or binary.
+intcode convert turns a text program into the binary format, or back.";
+
+/// Where a day's puzzle input comes from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Input {
+  /// The input checked in under `days/resources`.
+  Builtin,
+  Stdin,
+  File(PathBuf),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RunOptions {
+  pub day: Option<u32>,
+  pub part: Option<usize>,
+  pub input: Input,
+  /// How many days to run at once; `None` picks one per CPU.
+  pub jobs: Option<usize>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VerifyOptions {
+  pub day: Option<u32>,
+  /// Replaces the checked-in `days/resources/answers.toml`.
+  pub answers: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BenchOptions {
+  pub day: Option<u32>,
+  pub input: Input,
+  pub runs: usize,
+  /// Print one line of JSON per day instead of a table.
+  pub json: bool,
+  /// Time the Intcode engines on day 9's program rather than the days.
+  pub intcode: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IntcodeOptions {
+  /// Never [`Input::Builtin`].
+  pub program: Input,
+  pub inputs: Vec<ComputerWord>,
+}
+
+/// A program file and the input to queue for it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProgramOptions {
+  pub program: PathBuf,
+  pub inputs: Vec<ComputerWord>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Command {
+  Run(RunOptions),
+  List,
+  Verify(VerifyOptions),
+  Bench(BenchOptions),
+  Debug(ProgramOptions),
+  /// Print the control-flow graph of the program at this path.
+  Cfg(PathBuf),
+  Profile(ProgramOptions),
+  /// Play the ASCII program at this path in the terminal.
+  Play(PathBuf),
+  Intcode(IntcodeOptions),
+  /// Convert the program at the first path between text and binary,
+  /// writing it to the second.
+  Convert(PathBuf, PathBuf),
+  Help,
+}
+
+fn value<'a>(flag: &str, args: &mut impl Iterator<Item = &'a String>) -> Result<&'a str, String> {
+  args
+    .next()
+    .map(String::as_str)
+    .ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+  value
+    .parse()
+    .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+}
+
+fn input(value: &str) -> Input {
+  match value {
+    "-" => Input::Stdin,
+    path => Input::File(PathBuf::from(path)),
+  }
+}
+
+fn parse_verify(args: &[String]) -> Result<Command, String> {
+  let mut options = VerifyOptions {
+    day: None,
+    answers: None,
+  };
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-d" | "--day" => options.day = Some(number(arg, value(arg, &mut args)?)?),
+      "-a" | "--answers" => options.answers = Some(PathBuf::from(value(arg, &mut args)?)),
+      other => return Err(format!("Unexpected argument '{}'", other)),
+    }
+  }
+  Ok(Command::Verify(options))
+}
+
+fn parse_bench(args: &[String]) -> Result<Command, String> {
+  let mut options = BenchOptions {
+    day: None,
+    input: Input::Builtin,
+    runs: 10,
+    json: false,
+    intcode: false,
+  };
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-d" | "--day" => options.day = Some(number(arg, value(arg, &mut args)?)?),
+      "-i" | "--input" => options.input = input(value(arg, &mut args)?),
+      "-r" | "--runs" => match number(arg, value(arg, &mut args)?)? {
+        0 => return Err("--runs needs at least one run".to_string()),
+        runs => options.runs = runs,
+      },
+      "--json" => options.json = true,
+      "--intcode" => options.intcode = true,
+      other => return Err(format!("Unexpected argument '{}'", other)),
+    }
+  }
+
+  if options.intcode && options.day.is_some() {
+    return Err("--intcode always runs day 9".to_string());
+  }
+  if options.day.is_none() && !options.intcode && options.input != Input::Builtin {
+    return Err("--input needs --day".to_string());
+  }
+  Ok(Command::Bench(options))
+}
+
+/// Parses `<program> [inputs...]` for `command`.
+fn parse_program(command: &str, args: &[String]) -> Result<ProgramOptions, String> {
+  let (program, inputs) = args
+    .split_first()
+    .ok_or_else(|| format!("{} needs a program", command))?;
+  let inputs = inputs
+    .iter()
+    .map(|input| {
+      input
+        .parse()
+        .map_err(|_| format!("Invalid input '{}'", input))
+    })
+    .collect::<Result<_, _>>()?;
+  Ok(ProgramOptions {
+    program: PathBuf::from(program),
+    inputs,
+  })
+}
+
+fn parse_intcode(args: &[String]) -> Result<Command, String> {
+  match args.first().map(String::as_str) {
+    Some("-h") | Some("--help") => return Ok(Command::Help),
+    Some("run") => {}
+    Some("convert") => match &args[1..] {
+      [from, to] => return Ok(Command::Convert(PathBuf::from(from), PathBuf::from(to))),
+      _ => return Err("intcode convert needs a program and an output".to_string()),
+    },
+    Some(other) => return Err(format!("Unknown intcode command '{}'", other)),
+    None => return Err("intcode needs a command".to_string()),
+  }
+
+  let mut program = None;
+  let mut inputs = Vec::new();
+  let mut args = args[1..].iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-i" | "--input" => {
+        inputs = value(arg, &mut args)?
+          .split(',')
+          .map(|input| number(arg, input.trim()))
+          .collect::<Result<_, _>>()?
+      }
+      path if program.is_none() => program = Some(input(path)),
+      other => return Err(format!("Unexpected argument '{}'", other)),
+    }
+  }
+
+  Ok(Command::Intcode(IntcodeOptions {
+    program: program.ok_or("intcode run needs a program")?,
+    inputs,
+  }))
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+  match args.first().map(String::as_str) {
+    Some("list") if args.len() == 1 => return Ok(Command::List),
+    Some("debug") => return parse_program("debug", &args[1..]).map(Command::Debug),
+    Some("profile") => return parse_program("profile", &args[1..]).map(Command::Profile),
+    Some("cfg") => match &args[1..] {
+      [path] => return Ok(Command::Cfg(PathBuf::from(path))),
+      _ => return Err("cfg needs exactly one program".to_string()),
+    },
+    Some("play") => match &args[1..] {
+      [path] => return Ok(Command::Play(PathBuf::from(path))),
+      _ => return Err("play needs exactly one program".to_string()),
+    },
+    Some("intcode") => return parse_intcode(&args[1..]),
+    Some("verify") => return parse_verify(&args[1..]),
+    Some("bench") => return parse_bench(&args[1..]),
+    _ => {}
+  }
+
+  let mut options = RunOptions {
+    day: None,
+    part: None,
+    input: Input::Builtin,
+    jobs: None,
+  };
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-d" | "--day" => options.day = Some(number(arg, value(arg, &mut args)?)?),
+      "-p" | "--part" => match number(arg, value(arg, &mut args)?)? {
+        part @ 1..=2 => options.part = Some(part),
+        part => return Err(format!("There is no part {}", part)),
+      },
+      "-j" | "--jobs" => match number(arg, value(arg, &mut args)?)? {
+        0 => return Err("--jobs needs at least one job".to_string()),
+        jobs => options.jobs = Some(jobs),
+      },
+      "-i" | "--input" => options.input = input(value(arg, &mut args)?),
+      other => return Err(format!("Unexpected argument '{}'", other)),
+    }
+  }
+
+  if options.day.is_none() && options.input != Input::Builtin {
+    return Err("--input needs --day".to_string());
+  }
+  Ok(Command::Run(options))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::computer;
+
+  fn parse_str(args: &str) -> Result<Command, String> {
+    let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    parse(&args)
+  }
+
+  #[test]
+  fn test_run_options() {
+    assert_eq!(
+      parse_str(""),
+      Ok(Command::Run(RunOptions {
+        day: None,
+        part: None,
+        input: Input::Builtin,
+        jobs: None
+      }))
+    );
+    assert_eq!(
+      parse_str("--day 3 --part 2 --input path/to/file --jobs 4"),
+      Ok(Command::Run(RunOptions {
+        day: Some(3),
+        part: Some(2),
+        input: Input::File(PathBuf::from("path/to/file")),
+        jobs: Some(4)
+      }))
+    );
+    assert_eq!(
+      parse_str("-d 5 -i -"),
+      Ok(Command::Run(RunOptions {
+        day: Some(5),
+        part: None,
+        input: Input::Stdin,
+        jobs: None
+      }))
+    );
+  }
+
+  #[test]
+  fn test_commands() {
+    assert_eq!(parse_str("list"), Ok(Command::List));
+    assert_eq!(
+      parse_str("profile program.txt 1"),
+      Ok(Command::Profile(ProgramOptions {
+        program: PathBuf::from("program.txt"),
+        inputs: computer::words([1])
+      }))
+    );
+    assert_eq!(
+      parse_str("cfg program.txt"),
+      Ok(Command::Cfg(PathBuf::from("program.txt")))
+    );
+    assert_eq!(parse_str("--help"), Ok(Command::Help));
+    assert_eq!(
+      parse_str("verify"),
+      Ok(Command::Verify(VerifyOptions {
+        day: None,
+        answers: None
+      }))
+    );
+    assert_eq!(
+      parse_str("bench --day 10 --input big.txt --runs 3 --json"),
+      Ok(Command::Bench(BenchOptions {
+        day: Some(10),
+        input: Input::File(PathBuf::from("big.txt")),
+        runs: 3,
+        json: true,
+        intcode: false
+      }))
+    );
+    assert_eq!(
+      parse_str("bench --intcode --input boost.txt"),
+      Ok(Command::Bench(BenchOptions {
+        day: None,
+        input: Input::File(PathBuf::from("boost.txt")),
+        runs: 10,
+        json: false,
+        intcode: true
+      }))
+    );
+    assert_eq!(
+      parse_str("verify --day 2 --answers answers.toml"),
+      Ok(Command::Verify(VerifyOptions {
+        day: Some(2),
+        answers: Some(PathBuf::from("answers.toml"))
+      }))
+    );
+    assert_eq!(
+      parse_str("intcode run program.txt --input 1,-2,3"),
+      Ok(Command::Intcode(IntcodeOptions {
+        program: Input::File(PathBuf::from("program.txt")),
+        inputs: computer::words([1, -2, 3])
+      }))
+    );
+    assert_eq!(
+      parse_str("intcode run -"),
+      Ok(Command::Intcode(IntcodeOptions {
+        program: Input::Stdin,
+        inputs: vec![]
+      }))
+    );
+    #[cfg(feature = "bigint")]
+    assert_eq!(
+      parse_str("intcode run - --input 18446744073709551616"),
+      Ok(Command::Intcode(IntcodeOptions {
+        program: Input::Stdin,
+        inputs: vec![ComputerWord::from(1u64 << 63) * 2]
+      }))
+    );
+    assert_eq!(
+      parse_str("intcode convert program.txt program.icb"),
+      Ok(Command::Convert(
+        PathBuf::from("program.txt"),
+        PathBuf::from("program.icb")
+      ))
+    );
+    assert_eq!(
+      parse_str("debug program.txt 1 -2"),
+      Ok(Command::Debug(ProgramOptions {
+        program: PathBuf::from("program.txt"),
+        inputs: computer::words([1, -2])
+      }))
+    );
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(parse_str("--day"), Err("--day needs a value".to_string()));
+    assert_eq!(
+      parse_str("--day three"),
+      Err("--day expects a number, got 'three'".to_string())
+    );
+    assert_eq!(parse_str("--part 3"), Err("There is no part 3".to_string()));
+    assert_eq!(
+      parse_str("--input file"),
+      Err("--input needs --day".to_string())
+    );
+    assert_eq!(
+      parse_str("list extra"),
+      Err("Unexpected argument 'list'".to_string())
+    );
+    assert_eq!(
+      parse_str("verify --part 1"),
+      Err("Unexpected argument '--part'".to_string())
+    );
+    assert_eq!(parse_str("debug"), Err("debug needs a program".to_string()));
+    assert_eq!(
+      parse_str("profile a.txt 1 x"),
+      Err("Invalid input 'x'".to_string())
+    );
+    assert_eq!(
+      parse_str("cfg"),
+      Err("cfg needs exactly one program".to_string())
+    );
+    assert_eq!(
+      parse_str("intcode run"),
+      Err("intcode run needs a program".to_string())
+    );
+    assert_eq!(
+      parse_str("intcode run a.txt b.txt"),
+      Err("Unexpected argument 'b.txt'".to_string())
+    );
+    assert_eq!(
+      parse_str("intcode run a.txt --input 1,x"),
+      Err("--input expects a number, got 'x'".to_string())
+    );
+    assert_eq!(
+      parse_str("intcode convert a.txt"),
+      Err("intcode convert needs a program and an output".to_string())
+    );
+    assert_eq!(
+      parse_str("intcode walk a.txt"),
+      Err("Unknown intcode command 'walk'".to_string())
+    );
+    assert_eq!(
+      parse_str("play a.txt b.txt"),
+      Err("play needs exactly one program".to_string())
+    );
+    assert_eq!(
+      parse_str("--jobs 0"),
+      Err("--jobs needs at least one job".to_string())
+    );
+    assert_eq!(
+      parse_str("bench --runs 0"),
+      Err("--runs needs at least one run".to_string())
+    );
+    assert_eq!(
+      parse_str("bench --input -"),
+      Err("--input needs --day".to_string())
+    );
+    assert_eq!(
+      parse_str("bench --intcode --day 5"),
+      Err("--intcode always runs day 9".to_string())
+    );
+  }
+}
@@ -0,0 +1,183 @@
+use std::io::{self, BufRead, Write};
+
+use num::ToPrimitive;
+
+use super::{Computer, ComputerError, ComputerWord, Status};
+
+/// A stretch of output from an ASCII program.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AsciiOutput {
+  Text(String),
+  /// A word too big to be ASCII, which such programs use for their answer.
+  Value(ComputerWord),
+}
+
+/// What a program said before it stopped to wait for input or halted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Reply {
+  pub output: Vec<AsciiOutput>,
+  pub halted: bool,
+}
+
+impl Reply {
+  /// All the text, leaving out any values.
+  pub fn text(&self) -> String {
+    self
+      .output
+      .iter()
+      .filter_map(|output| match output {
+        AsciiOutput::Text(text) => Some(text.as_str()),
+        AsciiOutput::Value(_) => None,
+      })
+      .collect()
+  }
+
+  pub fn values(&self) -> Vec<&ComputerWord> {
+    self
+      .output
+      .iter()
+      .filter_map(|output| match output {
+        AsciiOutput::Value(value) => Some(value),
+        AsciiOutput::Text(_) => None,
+      })
+      .collect()
+  }
+}
+
+/// Talks to a program that reads and writes lines of ASCII, one character
+/// per word.
+pub struct Ascii {
+  computer: Computer,
+}
+
+impl Ascii {
+  pub fn new(computer: Computer) -> Self {
+    Ascii { computer }
+  }
+
+  pub fn computer(&self) -> &Computer {
+    &self.computer
+  }
+
+  pub fn into_computer(self) -> Computer {
+    self.computer
+  }
+
+  /// Queues `line` as input, followed by a newline.
+  pub fn send_line(&mut self, line: &str) {
+    for c in line.chars().chain(Some('\n')) {
+      self.computer.push_input(ComputerWord::from(u32::from(c)));
+    }
+  }
+
+  /// Runs until the program wants input it hasn't been sent, or halts.
+  pub fn read(&mut self) -> Result<Reply, ComputerError> {
+    let mut output = Vec::new();
+    loop {
+      let value = match self.computer.resume()? {
+        Status::Output(value) => value,
+        Status::NeedsInput => {
+          return Ok(Reply {
+            output,
+            halted: false,
+          })
+        }
+        Status::Halted => {
+          return Ok(Reply {
+            output,
+            halted: true,
+          })
+        }
+      };
+
+      match (value.to_u8().filter(u8::is_ascii), output.last_mut()) {
+        (Some(c), Some(AsciiOutput::Text(text))) => text.push(char::from(c)),
+        (Some(c), _) => output.push(AsciiOutput::Text(char::from(c).to_string())),
+        (None, _) => output.push(AsciiOutput::Value(value)),
+      }
+    }
+  }
+
+  /// Sends `line` and reads the reply to it.
+  pub fn ask(&mut self, line: &str) -> Result<Reply, ComputerError> {
+    self.send_line(line);
+    self.read()
+  }
+}
+
+/// Plays the program in a terminal: its text goes to `out` as is, values on
+/// a line of their own, and whenever it waits a line from `input` is sent.
+///
+/// Stops when the program halts or `input` runs out.
+pub fn play(ascii: &mut Ascii, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+  let mut lines = input.lines();
+  loop {
+    let reply = ascii
+      .read()
+      .map_err(|error| io::Error::other(error.to_string()))?;
+    for output in &reply.output {
+      match output {
+        AsciiOutput::Text(text) => write!(out, "{}", text)?,
+        AsciiOutput::Value(value) => writeln!(out, "{}", value)?,
+      }
+    }
+    out.flush()?;
+    if reply.halted {
+      return Ok(());
+    }
+
+    match lines.next() {
+      Some(line) => ascii.send_line(&line?),
+      None => return Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  // Prints "?", then echoes each character of a line until it reads a
+  // newline, and finally outputs 1000 and halts.
+  const ECHO: &str = "104,63,3,20,4,20,1008,20,10,21,1006,21,2,104,1000,99,0,0,0,0,0,0";
+
+  fn echo() -> Ascii {
+    Ascii::new(Computer::new(parse_op_stack(ECHO).unwrap()))
+  }
+
+  #[test]
+  fn test_read_and_ask() {
+    let mut ascii = echo();
+    assert_eq!(
+      ascii.read(),
+      Ok(Reply {
+        output: vec![AsciiOutput::Text("?".to_string())],
+        halted: false
+      })
+    );
+
+    let reply = ascii.ask("hi").unwrap();
+    assert!(reply.halted);
+    assert_eq!(reply.text(), "hi\n");
+    assert_eq!(reply.values(), vec![&ComputerWord::from(1000)]);
+    assert_eq!(
+      reply.output[1],
+      AsciiOutput::Value(ComputerWord::from(1000))
+    );
+  }
+
+  #[test]
+  fn test_play() {
+    let mut out = Vec::new();
+    play(&mut echo(), "hello\nunused\n".as_bytes(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "?hello\n1000\n");
+
+    // Running out of input leaves the program waiting.
+    let mut ascii = echo();
+    let mut out = Vec::new();
+    play(&mut ascii, "".as_bytes(), &mut out).unwrap();
+    assert_eq!(out, b"?");
+    assert_eq!(ascii.read().map(|reply| reply.halted), Ok(false));
+  }
+}
@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use super::{BinaryOp, ComputerWord, InstructionSize, JumpOp, OpArg, OpCode};
+
+/// Where in the source an assembly error happened; lines and columns start at 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AssembleError {
+  pub line: usize,
+  pub column: usize,
+  pub message: String,
+}
+
+impl Display for AssembleError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    write!(f, "{}:{}: {}", self.line, self.column, self.message)
+  }
+}
+
+impl std::error::Error for AssembleError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+  Atom(String),
+  Comma,
+  Arrow,
+  Colon,
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+  token: Token,
+  column: usize,
+}
+
+/// Splits a line into tokens, dropping anything after a `;` comment.
+fn tokenize(line: &str) -> Vec<Spanned> {
+  let chars: Vec<char> = line.chars().collect();
+  let mut tokens = Vec::new();
+  let mut idx = 0;
+
+  while idx < chars.len() {
+    let column = idx + 1;
+    match chars[idx] {
+      ';' => break,
+      c if c.is_whitespace() => idx += 1,
+      ',' => {
+        tokens.push(Spanned {
+          token: Token::Comma,
+          column,
+        });
+        idx += 1;
+      }
+      ':' => {
+        tokens.push(Spanned {
+          token: Token::Colon,
+          column,
+        });
+        idx += 1;
+      }
+      '-' if chars.get(idx + 1) == Some(&'>') => {
+        tokens.push(Spanned {
+          token: Token::Arrow,
+          column,
+        });
+        idx += 2;
+      }
+      _ => {
+        let start = idx;
+        while idx < chars.len() {
+          let c = chars[idx];
+          let is_arrow = c == '-' && chars.get(idx + 1) == Some(&'>');
+          if c.is_whitespace() || ",:;".contains(c) || is_arrow {
+            break;
+          }
+          idx += 1;
+        }
+        tokens.push(Spanned {
+          token: Token::Atom(chars[start..idx].iter().collect()),
+          column,
+        });
+      }
+    }
+  }
+
+  tokens
+}
+
+fn is_label(name: &str) -> bool {
+  let mut chars = name.chars();
+  let starts_ok = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_');
+  starts_ok && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !is_relative(name)
+}
+
+fn is_relative(name: &str) -> bool {
+  name.starts_with('r') && ComputerWord::from_str(&name[1..]).is_ok()
+}
+
+/// A number, or a label that is resolved to its address once every line has been read.
+#[derive(Debug, Clone)]
+enum Value {
+  Number(ComputerWord),
+  Label { name: String, column: usize },
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+  Position(Value),
+  Immediate(Value),
+  Relative(ComputerWord),
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+  Op {
+    mnemonic: String,
+    operands: Vec<Operand>,
+  },
+  Data(Vec<Value>),
+}
+
+impl InstructionSize for Statement {
+  fn size(&self) -> usize {
+    match self {
+      Statement::Op { operands, .. } => operands.len() + 1,
+      Statement::Data(values) => values.len(),
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+  Binary,
+  Read,
+  Single,
+  Jump,
+  Nothing,
+}
+
+fn shape(mnemonic: &str) -> Option<Shape> {
+  match mnemonic {
+    "add" | "mul" | "lt" | "eq" => Some(Shape::Binary),
+    "in" => Some(Shape::Read),
+    "out" | "arb" => Some(Shape::Single),
+    "jnz" | "jz" => Some(Shape::Jump),
+    "hlt" => Some(Shape::Nothing),
+    _ => None,
+  }
+}
+
+struct LineParser<'a> {
+  tokens: &'a [Spanned],
+  position: usize,
+  line: usize,
+  end_column: usize,
+}
+
+impl<'a> LineParser<'a> {
+  fn error(&self, column: usize, message: impl Into<String>) -> AssembleError {
+    AssembleError {
+      line: self.line,
+      column,
+      message: message.into(),
+    }
+  }
+
+  fn column(&self) -> usize {
+    self
+      .tokens
+      .get(self.position)
+      .map_or(self.end_column, |t| t.column)
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position).map(|t| &t.token)
+  }
+
+  fn expect(&mut self, expected: Token, description: &str) -> Result<(), AssembleError> {
+    if self.peek() == Some(&expected) {
+      self.position += 1;
+      Ok(())
+    } else {
+      Err(self.error(self.column(), format!("Expected {}", description)))
+    }
+  }
+
+  fn atom(&mut self) -> Result<(String, usize), AssembleError> {
+    match self.tokens.get(self.position) {
+      Some(Spanned {
+        token: Token::Atom(text),
+        column,
+      }) => {
+        self.position += 1;
+        Ok((text.clone(), *column))
+      }
+      _ => Err(self.error(self.column(), "Expected an operand")),
+    }
+  }
+
+  fn value(&self, text: &str, column: usize) -> Result<Value, AssembleError> {
+    if let Ok(number) = ComputerWord::from_str(text) {
+      Ok(Value::Number(number))
+    } else if is_label(text) {
+      Ok(Value::Label {
+        name: text.to_string(),
+        column,
+      })
+    } else {
+      Err(self.error(column, format!("Invalid value '{}'", text)))
+    }
+  }
+
+  fn operand(&mut self) -> Result<Operand, AssembleError> {
+    let (text, column) = self.atom()?;
+    if let Some(rest) = text.strip_prefix('@') {
+      Ok(Operand::Position(self.value(rest, column + 1)?))
+    } else if is_relative(&text) {
+      Ok(Operand::Relative(
+        ComputerWord::from_str(&text[1..]).unwrap(),
+      ))
+    } else {
+      Ok(Operand::Immediate(self.value(&text, column)?))
+    }
+  }
+
+  fn destination(&mut self) -> Result<Operand, AssembleError> {
+    let column = self.column();
+    match self.operand()? {
+      Operand::Immediate(_) => Err(self.error(column, "Can't write to an immediate operand")),
+      destination => Ok(destination),
+    }
+  }
+
+  fn finish(&self) -> Result<(), AssembleError> {
+    if self.position < self.tokens.len() {
+      Err(self.error(self.column(), "Unexpected trailing input"))
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Leading `name:` labels. A numeric prefix like `0010:` is an address
+  /// annotation, as printed by the disassembler.
+  fn labels(&mut self) -> Vec<(String, usize)> {
+    let mut labels = Vec::new();
+    while let (Some(Token::Atom(name)), Some(Token::Colon)) = (
+      self.tokens.get(self.position).map(|t| &t.token),
+      self.tokens.get(self.position + 1).map(|t| &t.token),
+    ) {
+      labels.push((name.clone(), self.tokens[self.position].column));
+      self.position += 2;
+    }
+    labels
+  }
+
+  fn statement(&mut self) -> Result<Option<Statement>, AssembleError> {
+    if self.peek().is_none() {
+      return Ok(None);
+    }
+
+    let (mnemonic, column) = self.atom()?;
+    if mnemonic == "data" {
+      let mut values = Vec::new();
+      loop {
+        let (text, column) = self.atom()?;
+        values.push(self.value(&text, column)?);
+        if self.peek() != Some(&Token::Comma) {
+          break;
+        }
+        self.position += 1;
+      }
+      self.finish()?;
+      return Ok(Some(Statement::Data(values)));
+    }
+
+    let shape = shape(&mnemonic)
+      .ok_or_else(|| self.error(column, format!("Unknown mnemonic '{}'", mnemonic)))?;
+    let operands = match shape {
+      Shape::Binary => {
+        let op1 = self.operand()?;
+        self.expect(Token::Comma, "','")?;
+        let op2 = self.operand()?;
+        self.expect(Token::Arrow, "'->'")?;
+        vec![op1, op2, self.destination()?]
+      }
+      Shape::Read => {
+        self.expect(Token::Arrow, "'->'")?;
+        vec![self.destination()?]
+      }
+      Shape::Single => vec![self.operand()?],
+      Shape::Jump => {
+        let test = self.operand()?;
+        self.expect(Token::Comma, "','")?;
+        vec![test, self.operand()?]
+      }
+      Shape::Nothing => vec![],
+    };
+    self.finish()?;
+
+    Ok(Some(Statement::Op { mnemonic, operands }))
+  }
+}
+
+struct Resolver<'a> {
+  labels: &'a HashMap<String, usize>,
+  line: usize,
+}
+
+impl<'a> Resolver<'a> {
+  fn value(&self, value: &Value) -> Result<ComputerWord, AssembleError> {
+    match value {
+      Value::Number(number) => Ok(number.clone()),
+      Value::Label { name, column } => self
+        .labels
+        .get(name)
+        .map(|&address| ComputerWord::from(address as i64))
+        .ok_or_else(|| AssembleError {
+          line: self.line,
+          column: *column,
+          message: format!("Undefined label '{}'", name),
+        }),
+    }
+  }
+
+  fn arg(&self, operand: &Operand) -> Result<OpArg, AssembleError> {
+    Ok(match operand {
+      Operand::Position(value) => OpArg::Reference(self.value(value)?),
+      Operand::Immediate(value) => OpArg::Literal(self.value(value)?),
+      Operand::Relative(offset) => OpArg::Relative(offset.clone()),
+    })
+  }
+
+  fn op_code(&self, mnemonic: &str, operands: &[Operand]) -> Result<OpCode, AssembleError> {
+    let mut args = operands
+      .iter()
+      .map(|operand| self.arg(operand))
+      .collect::<Result<Vec<_>, _>>()?
+      .into_iter();
+    let mut next = || args.next().unwrap();
+
+    Ok(match mnemonic {
+      "add" => OpCode::Add(BinaryOp::new(next(), next(), next())),
+      "mul" => OpCode::Mul(BinaryOp::new(next(), next(), next())),
+      "lt" => OpCode::LessThan(BinaryOp::new(next(), next(), next())),
+      "eq" => OpCode::Equals(BinaryOp::new(next(), next(), next())),
+      "in" => OpCode::ReadInput { to: next() },
+      "out" => OpCode::SaveOutput { from: next() },
+      "jnz" => OpCode::JumpIfNonZero(JumpOp::new(next(), next())),
+      "jz" => OpCode::JumpIfZero(JumpOp::new(next(), next())),
+      "arb" => OpCode::RelativeAdjustment(next()),
+      "hlt" => OpCode::Done,
+      _ => unreachable!("Mnemonics are checked while parsing"),
+    })
+  }
+}
+
+/// Assembles source text into a program for [`super::Computer::new`].
+///
+/// Each line is `[label:]... [mnemonic operands] [; comment]`. Operands are
+/// `@addr` for position mode, `rN` for relative mode and a bare number for
+/// immediate mode; a label name can stand in for any number. `data` emits
+/// its comma-separated values as-is.
+pub fn assemble(source: &str) -> Result<Vec<ComputerWord>, AssembleError> {
+  let mut labels: HashMap<String, usize> = HashMap::new();
+  let mut statements = Vec::new();
+  let mut address = 0;
+
+  for (idx, line) in source.lines().enumerate() {
+    let tokens = tokenize(line);
+    let mut parser = LineParser {
+      tokens: &tokens,
+      position: 0,
+      line: idx + 1,
+      end_column: line.chars().count() + 1,
+    };
+
+    for (name, column) in parser.labels() {
+      if let Ok(annotated) = usize::from_str(&name) {
+        if annotated != address {
+          return Err(parser.error(
+            column,
+            format!(
+              "Address {} doesn't match actual address {}",
+              annotated, address
+            ),
+          ));
+        }
+      } else if !is_label(&name) {
+        return Err(parser.error(column, format!("Invalid label '{}'", name)));
+      } else if labels.insert(name.clone(), address).is_some() {
+        return Err(parser.error(column, format!("Duplicate label '{}'", name)));
+      }
+    }
+
+    if let Some(statement) = parser.statement()? {
+      address += statement.size();
+      statements.push((idx + 1, statement));
+    }
+  }
+
+  let mut program = Vec::with_capacity(address);
+  for (line, statement) in statements {
+    let resolver = Resolver {
+      labels: &labels,
+      line,
+    };
+    match statement {
+      Statement::Op { mnemonic, operands } => {
+        program.extend(resolver.op_code(&mnemonic, &operands)?.encode())
+      }
+      Statement::Data(values) => {
+        for value in &values {
+          program.push(resolver.value(value)?);
+        }
+      }
+    }
+  }
+
+  Ok(program)
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::disassembler::listing;
+  use crate::computer::{Computer, ComputerState};
+
+  #[test]
+  fn test_encodes_modes() {
+    assert_eq!(
+      assemble("mul @4, 3 -> @4\nhlt"),
+      Ok(vec![1002, 4, 3, 4, 99])
+    );
+    assert_eq!(
+      assemble("add @4, 3 -> r2\nin -> r-1\nout 7\njz r0, 12\narb -5"),
+      Ok(vec![21001, 4, 3, 2, 203, -1, 104, 7, 1206, 0, 12, 109, -5])
+    );
+  }
+
+  #[test]
+  fn test_labels_and_data() {
+    let source = "
+      ; Counts down from the input, printing each value.
+      in -> @counter
+    loop:
+      out @counter
+      add @counter, -1 -> @counter
+      jnz @counter, loop
+      hlt
+    counter: data 0
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(
+      program,
+      vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+    );
+    let output = Computer::new(program).add_input(3).run().unwrap();
+    assert_eq!(output, vec![3, 2, 1]);
+  }
+
+  #[test]
+  fn test_round_trips_listing() {
+    let program = vec![1002, 4, 3, 4, 33, 109, -1, 204, 0, 1105, 1, 0, 99];
+    let text = listing(&ComputerState::from(program.clone()));
+    assert_eq!(assemble(&text), Ok(program));
+  }
+
+  #[test]
+  fn test_errors() {
+    let error = |source: &str| {
+      let error = assemble(source).unwrap_err();
+      (error.line, error.column, error.message)
+    };
+
+    assert_eq!(
+      error("hlt\n  frob 1"),
+      (2, 3, "Unknown mnemonic 'frob'".to_string())
+    );
+    assert_eq!(
+      error("add 1, 2 -> 3"),
+      (1, 13, "Can't write to an immediate operand".to_string())
+    );
+    assert_eq!(error("add 1 2 -> @3"), (1, 7, "Expected ','".to_string()));
+    assert_eq!(
+      error("jz 0, nowhere"),
+      (1, 7, "Undefined label 'nowhere'".to_string())
+    );
+    assert_eq!(error("out @x1!"), (1, 6, "Invalid value 'x1!'".to_string()));
+    assert_eq!(
+      error("a: hlt\na: hlt"),
+      (2, 1, "Duplicate label 'a'".to_string())
+    );
+    assert_eq!(error("out"), (1, 4, "Expected an operand".to_string()));
+  }
+}
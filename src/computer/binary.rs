@@ -0,0 +1,452 @@
+use std::collections::VecDeque;
+
+use num::{CheckedAdd, CheckedMul, Integer, One, Signed, ToPrimitive, Zero};
+
+use super::loader::{self, LoadError};
+use super::{Computer, ComputerError, ComputerState, ComputerWord};
+
+/// Every binary file starts with this, which no text program can.
+const MAGIC: &[u8; 4] = b"INTC";
+const VERSION: u8 = 1;
+/// Set in the flags byte when the file ends with a checksum.
+const CHECKSUM: u8 = 1;
+
+/// What a binary file holds, stored after the version.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Kind {
+  Program = 0,
+  Snapshot = 1,
+}
+
+/// Names the kind of file in a kind byte, for errors.
+fn describe(kind: u8) -> String {
+  match kind {
+    0 => "a program".to_string(),
+    1 => "a snapshot".to_string(),
+    kind => format!("unknown kind {}", kind),
+  }
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+  let (mut a, mut b) = (1u32, 0u32);
+  for &byte in bytes {
+    a = (a + u32::from(byte)) % 65521;
+    b = (b + a) % 65521;
+  }
+  (b << 16) | a
+}
+
+/// Builds a binary file: a header of the magic, version, kind and flags,
+/// then the body, then the Adler-32 of everything before it if asked for.
+///
+/// Counts and addresses are LEB128 varints. Words are varints too, with
+/// the sign in the lowest bit of the first byte; a negative word `n` is
+/// stored as the magnitude of `n + 1`, so `i64::MIN` still fits.
+struct Writer {
+  bytes: Vec<u8>,
+  checksum: bool,
+}
+
+impl Writer {
+  fn new(kind: Kind, checksum: bool) -> Self {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.push(kind as u8);
+    bytes.push(if checksum { CHECKSUM } else { 0 });
+    Writer { bytes, checksum }
+  }
+
+  fn count(&mut self, mut value: usize) {
+    while value >= 0x80 {
+      self.bytes.push(value as u8 | 0x80);
+      value >>= 7;
+    }
+    self.bytes.push(value as u8);
+  }
+
+  fn word(&mut self, word: &ComputerWord) {
+    let negative = word.is_negative();
+    let magnitude = match negative {
+      true => -(word.clone() + ComputerWord::one()),
+      false => word.clone(),
+    };
+    let (mut rest, low) = magnitude.div_rem(&ComputerWord::from(64));
+    let mut byte = low.to_u8().expect("Less than 64") << 1 | negative as u8;
+    while !rest.is_zero() {
+      self.bytes.push(byte | 0x80);
+      let (next, low) = rest.div_rem(&ComputerWord::from(128));
+      byte = low.to_u8().expect("Less than 128");
+      rest = next;
+    }
+    self.bytes.push(byte);
+  }
+
+  fn words<'a>(&mut self, words: impl ExactSizeIterator<Item = &'a ComputerWord>) {
+    self.count(words.len());
+    for word in words {
+      self.word(word);
+    }
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    if self.checksum {
+      let checksum = adler32(&self.bytes);
+      self.bytes.extend_from_slice(&checksum.to_be_bytes());
+    }
+    self.bytes
+  }
+}
+
+/// Reads back what a [`Writer`] wrote, with errors giving the byte offset.
+struct Reader<'a> {
+  bytes: &'a [u8],
+  offset: usize,
+}
+
+impl<'a> Reader<'a> {
+  /// Checks the header and any checksum, leaving the reader at the body.
+  fn open(bytes: &'a [u8], kind: Kind) -> Result<Self, ComputerError> {
+    let mut reader = Reader { bytes, offset: 0 };
+    if !bytes.starts_with(MAGIC) {
+      return Err(reader.error("not an Intcode binary"));
+    }
+    reader.offset = MAGIC.len();
+    let version = reader.byte()?;
+    if version != VERSION {
+      reader.offset -= 1;
+      return Err(reader.error(format!("unsupported version {}", version)));
+    }
+    let found = reader.byte()?;
+    if found != kind as u8 {
+      reader.offset -= 1;
+      return Err(reader.error(format!(
+        "expected {}, found {}",
+        describe(kind as u8),
+        describe(found)
+      )));
+    }
+    let flags = reader.byte()?;
+    if flags & !CHECKSUM != 0 {
+      reader.offset -= 1;
+      return Err(reader.error(format!("unknown flags {:#04x}", flags)));
+    }
+
+    if flags & CHECKSUM != 0 {
+      let body_end = bytes
+        .len()
+        .checked_sub(4)
+        .filter(|&end| end >= reader.offset)
+        .ok_or_else(|| reader.error("missing checksum"))?;
+      let mut stored = [0; 4];
+      stored.copy_from_slice(&bytes[body_end..]);
+      if u32::from_be_bytes(stored) != adler32(&bytes[..body_end]) {
+        return Err(ComputerError::InvalidBinary {
+          offset: body_end,
+          message: "checksum mismatch".to_string(),
+        });
+      }
+      reader.bytes = &bytes[..body_end];
+    }
+    Ok(reader)
+  }
+
+  fn error(&self, message: impl Into<String>) -> ComputerError {
+    ComputerError::InvalidBinary {
+      offset: self.offset,
+      message: message.into(),
+    }
+  }
+
+  fn byte(&mut self) -> Result<u8, ComputerError> {
+    let byte = *self
+      .bytes
+      .get(self.offset)
+      .ok_or_else(|| self.error("unexpected end of data"))?;
+    self.offset += 1;
+    Ok(byte)
+  }
+
+  fn count(&mut self) -> Result<usize, ComputerError> {
+    let start = self.offset;
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+      let byte = self.byte()?;
+      let bits = usize::from(byte & 0x7f);
+      if shift >= usize::BITS || (bits << shift) >> shift != bits {
+        self.offset = start;
+        return Err(self.error("count too big"));
+      }
+      value |= bits << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+      shift += 7;
+    }
+  }
+
+  fn word(&mut self) -> Result<ComputerWord, ComputerError> {
+    let start = self.offset;
+    let first = self.byte()?;
+    let negative = first & 1 == 1;
+    let mut magnitude = ComputerWord::from(first >> 1 & 0x3f);
+    // What the next byte's bits are worth, until that would overflow.
+    let mut scale = Some(ComputerWord::from(64));
+    let mut byte = first;
+    while byte & 0x80 != 0 {
+      byte = self.byte()?;
+      let bits = ComputerWord::from(byte & 0x7f);
+      if !bits.is_zero() {
+        magnitude = scale
+          .as_ref()
+          .and_then(|scale| CheckedMul::checked_mul(scale, &bits))
+          .and_then(|value| CheckedAdd::checked_add(&magnitude, &value))
+          .ok_or(ComputerError::InvalidBinary {
+            offset: start,
+            message: "word too big".to_string(),
+          })?;
+      }
+      scale = scale.and_then(|scale| CheckedMul::checked_mul(&scale, &ComputerWord::from(128)));
+    }
+    Ok(match negative {
+      true => -magnitude - ComputerWord::one(),
+      false => magnitude,
+    })
+  }
+
+  fn words(&mut self) -> Result<Vec<ComputerWord>, ComputerError> {
+    let count = self.count()?;
+    // Every word takes at least a byte, which bounds what to allocate.
+    if count > self.bytes.len() - self.offset {
+      return Err(self.error(format!("{} words won't fit in what's left", count)));
+    }
+    (0..count).map(|_| self.word()).collect()
+  }
+
+  fn finish(self) -> Result<(), ComputerError> {
+    match self.offset == self.bytes.len() {
+      true => Ok(()),
+      false => Err(self.error("unexpected data after the end")),
+    }
+  }
+}
+
+/// Whether `bytes` look like a binary file rather than text.
+pub fn is_binary(bytes: &[u8]) -> bool {
+  bytes.starts_with(MAGIC)
+}
+
+/// A program in the binary format, optionally followed by a checksum.
+pub fn encode(program: &[ComputerWord], checksum: bool) -> Vec<u8> {
+  let mut writer = Writer::new(Kind::Program, checksum);
+  writer.words(program.iter());
+  writer.finish()
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<ComputerWord>, ComputerError> {
+  let mut reader = Reader::open(bytes, Kind::Program)?;
+  let program = reader.words()?;
+  reader.finish()?;
+  Ok(program)
+}
+
+/// Converts a text program, as [`loader::parse`] reads it, to binary.
+pub fn from_text(text: &str, checksum: bool) -> Result<Vec<u8>, LoadError> {
+  Ok(encode(&loader::parse(text)?, checksum))
+}
+
+/// Converts a binary program to comma separated text.
+pub fn to_text(bytes: &[u8]) -> Result<String, ComputerError> {
+  let words: Vec<String> = decode(bytes)?.iter().map(ToString::to_string).collect();
+  Ok(words.join(",") + "\n")
+}
+
+impl Computer {
+  /// Saves the same state as [`Computer::snapshot`] in the binary format,
+  /// always with a checksum.
+  ///
+  /// After the header come the registers, the input and output, the length
+  /// of memory and then its stretches of non-zero words.
+  pub fn snapshot_bytes(&self) -> Vec<u8> {
+    let mut writer = Writer::new(Kind::Snapshot, true);
+    writer.count(self.instruction_pointer);
+    writer.word(&self.relative_base);
+    writer.words(self.input.iter());
+    writer.words(self.output.iter());
+    writer.count(self.state.len());
+    let runs: Vec<(usize, &[ComputerWord])> = self.state.runs().collect();
+    writer.count(runs.len());
+    for (start, words) in runs {
+      writer.count(start);
+      writer.words(words.iter());
+    }
+    writer.finish()
+  }
+
+  /// Rebuilds a computer from [`Computer::snapshot_bytes`].
+  pub fn restore_bytes(bytes: &[u8]) -> Result<Computer, ComputerError> {
+    let mut reader = Reader::open(bytes, Kind::Snapshot)?;
+    let instruction_pointer = reader.count()?;
+    let relative_base = reader.word()?;
+    let input = reader.words()?;
+    let output = reader.words()?;
+    let len = reader.count()?;
+
+    let mut state = ComputerState::new();
+    for _ in 0..reader.count()? {
+      let offset = reader.offset;
+      let start = reader.count()?;
+      let words = reader.words()?;
+      if start.checked_add(words.len()).is_none_or(|end| end > len) {
+        return Err(ComputerError::InvalidBinary {
+          offset,
+          message: "memory runs past its length".to_string(),
+        });
+      }
+      for (offset, word) in words.into_iter().enumerate() {
+        state.set(start + offset, word);
+      }
+    }
+    reader.finish()?;
+    state.grow_to(len);
+
+    Ok(Computer {
+      input: VecDeque::from(input),
+      output,
+      instruction_pointer,
+      relative_base,
+      ..Computer::from(state)
+    })
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::{from, Status};
+
+  #[test]
+  fn test_words() {
+    let program = vec![0, 1, -1, 63, -64, 64, 1000, i64::MAX, i64::MIN];
+    let bytes = encode(&program, false);
+    assert_eq!(&bytes[..7], b"INTC\x01\x00\x00");
+    assert_eq!(
+      &bytes[7..15],
+      &[9, 0x00, 0x02, 0x01, 0x7e, 0x7f, 0x80, 0x01]
+    );
+    assert_eq!(decode(&bytes), Ok(program.clone()));
+    assert_eq!(decode(&encode(&program, true)), Ok(program));
+  }
+
+  #[test]
+  fn test_text_round_trip() {
+    let text = include_str!("../days/resources/day09.txt");
+    let bytes = from_text(text, true).unwrap();
+    assert!(is_binary(&bytes));
+    assert!(bytes.len() < text.len() / 2);
+    assert_eq!(to_text(&bytes).unwrap(), text.trim().to_string() + "\n");
+    assert_eq!(
+      from_text("1,x", true).map(|_| ()),
+      loader::parse("1,x").map(|_| ())
+    );
+  }
+
+  #[test]
+  fn test_invalid() {
+    let error = |offset, message: &str| {
+      Err(ComputerError::InvalidBinary {
+        offset,
+        message: message.to_string(),
+      })
+    };
+
+    assert_eq!(decode(b"1,2,3"), error(0, "not an Intcode binary"));
+    assert_eq!(
+      decode(b"INTC\x02\x00\x00"),
+      error(4, "unsupported version 2")
+    );
+    assert_eq!(
+      decode(b"INTC\x01\x01\x00"),
+      error(5, "expected a program, found a snapshot")
+    );
+    assert_eq!(
+      decode(b"INTC\x01\x00\x00\x01\x80"),
+      error(9, "unexpected end of data")
+    );
+    assert_eq!(
+      decode(b"INTC\x01\x00\x00\x05\x00"),
+      error(8, "5 words won't fit in what's left")
+    );
+    assert_eq!(
+      decode(b"INTC\x01\x00\x00\x01\xff\xff\xff\xff\xff\xff\xff\xff\xff\x7f"),
+      error(8, "word too big")
+    );
+    assert_eq!(
+      decode(b"INTC\x01\x00\x00\x00\x00"),
+      error(8, "unexpected data after the end")
+    );
+
+    let mut bytes = encode(&[1, 2, 3], true);
+    bytes[9] = 7;
+    assert_eq!(decode(&bytes), error(11, "checksum mismatch"));
+  }
+
+  #[test]
+  fn test_snapshot_round_trip() {
+    // The same machine as the text snapshot test.
+    let mut computer = from("109,7,21101,1,2,5000,3,20,4,20,99")
+      .unwrap()
+      .add_input(8)
+      .add_input(9);
+    assert_eq!(computer.resume(), Ok(Status::Output(8)));
+    computer.output(&1);
+
+    let bytes = computer.snapshot_bytes();
+    assert!(bytes.len() < computer.snapshot().len() / 2);
+    let mut restored = Computer::restore_bytes(&bytes).unwrap();
+    assert_eq!(restored.snapshot(), computer.snapshot());
+    assert_eq!(restored.resume(), Ok(Status::Halted));
+    assert_eq!(restored.run(), Ok(vec![1]));
+
+    assert_eq!(
+      Computer::restore_bytes(&encode(&[], true)).map(|_| ()),
+      Err(ComputerError::InvalidBinary {
+        offset: 5,
+        message: "expected a snapshot, found a program".to_string()
+      })
+    );
+  }
+
+  #[test]
+  fn test_memory_past_its_length() {
+    // Three words starting just before the last address, checksum and all.
+    let mut writer = Writer::new(Kind::Snapshot, true);
+    writer.count(0);
+    writer.word(&0);
+    writer.words([].iter());
+    writer.words([].iter());
+    writer.count(usize::MAX);
+    writer.count(1);
+    writer.count(usize::MAX - 1);
+    writer.words([1, 2, 3].iter());
+    assert_eq!(
+      Computer::restore_bytes(&writer.finish()).map(|_| ()),
+      Err(ComputerError::InvalidBinary {
+        offset: 22,
+        message: "memory runs past its length".to_string()
+      })
+    );
+  }
+}
+
+#[cfg(all(test, feature = "bigint"))]
+mod bigint_tests {
+  use super::*;
+
+  #[test]
+  fn test_past_64_bits() {
+    let big = ComputerWord::from(3) << 200;
+    let program = vec![big.clone(), -big, ComputerWord::from(i64::MIN) - 1];
+    assert_eq!(decode(&encode(&program, true)), Ok(program));
+  }
+}
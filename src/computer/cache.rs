@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use super::memory::PAGE_SIZE;
+use super::{InstructionSize, OpCode};
+
+/// The longest instruction, in words.
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+/// Decoded instructions by address, so a loop only decodes its body once.
+///
+/// Like [`super::ComputerState`] it's split into pages, so caching an
+/// instruction far out in memory doesn't allocate everything before it.
+/// Entries are dropped as soon as any of their words are written, so the
+/// cache never disagrees with memory.
+#[derive(Debug, Clone)]
+pub(super) struct DecodeCache {
+  enabled: bool,
+  pages: BTreeMap<usize, Vec<Option<OpCode>>>,
+}
+
+impl Default for DecodeCache {
+  fn default() -> Self {
+    DecodeCache {
+      enabled: true,
+      pages: BTreeMap::new(),
+    }
+  }
+}
+
+impl DecodeCache {
+  pub(super) fn disabled() -> Self {
+    DecodeCache {
+      enabled: false,
+      ..DecodeCache::default()
+    }
+  }
+
+  pub(super) fn get(&self, address: usize) -> Option<&OpCode> {
+    self.pages.get(&(address / PAGE_SIZE))?[address % PAGE_SIZE].as_ref()
+  }
+
+  pub(super) fn insert(&mut self, address: usize, op_code: &OpCode) {
+    if !self.enabled {
+      return;
+    }
+    let page = self
+      .pages
+      .entry(address / PAGE_SIZE)
+      .or_insert_with(|| vec![None; PAGE_SIZE]);
+    page[address % PAGE_SIZE] = Some(op_code.clone());
+  }
+
+  /// Forgets every instruction that the word at `address` is part of.
+  pub(super) fn invalidate(&mut self, address: usize) {
+    if self.pages.is_empty() {
+      return;
+    }
+    for start in address.saturating_sub(MAX_INSTRUCTION_SIZE - 1)..=address {
+      if let Some(page) = self.pages.get_mut(&(start / PAGE_SIZE)) {
+        let slot = &mut page[start % PAGE_SIZE];
+        if slot
+          .as_ref()
+          .is_some_and(|op_code| start + op_code.size() > address)
+        {
+          *slot = None;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::OpArg;
+
+  #[test]
+  fn test_invalidate() {
+    let mut cache = DecodeCache::default();
+    cache.insert(0, &OpCode::RelativeAdjustment(OpArg::Literal(1)));
+    cache.insert(2, &OpCode::Done);
+    cache.invalidate(1);
+    assert_eq!(cache.get(0), None);
+    assert_eq!(cache.get(2), Some(&OpCode::Done));
+    cache.invalidate(100);
+    assert_eq!(cache.get(2), Some(&OpCode::Done));
+
+    // Far out in memory, and across a page boundary.
+    let far = 1 << 40;
+    cache.insert(far - 1, &OpCode::RelativeAdjustment(OpArg::Literal(1)));
+    assert_eq!(cache.pages.len(), 2);
+    cache.invalidate(far);
+    assert_eq!(cache.get(far - 1), None);
+
+    let mut disabled = DecodeCache::disabled();
+    disabled.insert(0, &OpCode::Done);
+    assert_eq!(disabled.get(0), None);
+  }
+}
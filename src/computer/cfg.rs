@@ -0,0 +1,386 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use num::{ToPrimitive, Zero};
+
+use super::{ComputerError, ComputerState, Instruction, InstructionSize, OpArg, OpCode};
+
+/// Where an edge out of a block leads.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Target {
+  Block(usize),
+  /// The jump target is read from memory, so it's only known at run time.
+  Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EdgeKind {
+  /// Carries on to the next instruction.
+  FallThrough,
+  /// A jump whose test is a literal that always takes it.
+  Jump,
+  /// A jump whose test is only known at run time.
+  Branch,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Edge {
+  pub target: Target,
+  pub kind: EdgeKind,
+}
+
+/// A run of instructions that's only ever entered at the top and left at the bottom.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Block {
+  pub start: usize,
+  pub instructions: Vec<(usize, OpCode)>,
+  pub edges: Vec<Edge>,
+  /// Set when the block runs into a word that doesn't decode.
+  pub invalid: Option<ComputerError>,
+}
+
+/// An instruction that writes to a fixed address inside reachable code.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SelfModification {
+  /// The instruction doing the writing.
+  pub address: usize,
+  /// The word it writes.
+  pub target: usize,
+  /// The instruction that word belongs to.
+  pub instruction: usize,
+}
+
+/// The blocks reachable from address zero and the jumps between them,
+/// found without running the program.
+///
+/// Jumps with a literal target are followed; those that read their target
+/// from memory lead to [`Target::Unknown`], and whatever they might reach
+/// isn't explored.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ControlFlowGraph {
+  pub blocks: BTreeMap<usize, Block>,
+  pub self_modifications: Vec<SelfModification>,
+}
+
+/// How the instruction at an address hands on control.
+fn exits(address: usize, op_code: &OpCode) -> Vec<Edge> {
+  let fall_through = Edge {
+    target: Target::Block(address + op_code.size()),
+    kind: EdgeKind::FallThrough,
+  };
+  let jump_if_zero = match op_code {
+    OpCode::Done => return vec![],
+    OpCode::JumpIfNonZero(_) => false,
+    OpCode::JumpIfZero(_) => true,
+    _ => return vec![fall_through],
+  };
+
+  let args = op_code.args();
+  let target = match args[1] {
+    OpArg::Literal(target) => target.to_usize().map_or(Target::Unknown, Target::Block),
+    _ => Target::Unknown,
+  };
+  match args[0] {
+    OpArg::Literal(test) if test.is_zero() == jump_if_zero => vec![Edge {
+      target,
+      kind: EdgeKind::Jump,
+    }],
+    OpArg::Literal(_) => vec![fall_through],
+    _ => vec![
+      Edge {
+        target,
+        kind: EdgeKind::Branch,
+      },
+      fall_through,
+    ],
+  }
+}
+
+impl ControlFlowGraph {
+  pub fn build(state: &ComputerState) -> Self {
+    // Find every reachable instruction, and the addresses that start a block:
+    // the entry point, jump targets and whatever follows a jump.
+    let mut instructions = BTreeMap::new();
+    let mut invalid = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    let mut pending = vec![0];
+    while let Some(address) = pending.pop() {
+      if instructions.contains_key(&address) || invalid.contains_key(&address) {
+        continue;
+      }
+      if address >= state.len() {
+        continue;
+      }
+      let op_code = match Instruction::at(state, address).decode() {
+        Ok(op_code) => op_code,
+        Err(error) => {
+          invalid.insert(address, error);
+          continue;
+        }
+      };
+
+      let edges = exits(address, &op_code);
+      let is_jump = matches!(op_code, OpCode::JumpIfNonZero(_) | OpCode::JumpIfZero(_));
+      for edge in &edges {
+        if let Target::Block(target) = edge.target {
+          if is_jump {
+            leaders.insert(target);
+          }
+          pending.push(target);
+        }
+      }
+      instructions.insert(address, op_code);
+    }
+
+    let mut blocks = BTreeMap::new();
+    for &start in &leaders {
+      let mut block = Block {
+        start,
+        instructions: Vec::new(),
+        edges: Vec::new(),
+        invalid: None,
+      };
+      let mut address = start;
+      loop {
+        if let Some(error) = invalid.get(&address) {
+          block.invalid = Some(error.clone());
+          break;
+        }
+        let op_code = match instructions.get(&address) {
+          Some(op_code) => op_code,
+          // Past the end of memory, which halts.
+          None => break,
+        };
+        block.instructions.push((address, op_code.clone()));
+        block.edges = exits(address, op_code);
+        address += op_code.size();
+        let falls_into_leader = leaders.contains(&address)
+          && block
+            .edges
+            .iter()
+            .any(|edge| edge.kind == EdgeKind::FallThrough);
+        if block.edges.len() != 1
+          || block.edges[0].kind != EdgeKind::FallThrough
+          || falls_into_leader
+        {
+          break;
+        }
+        block.edges.clear();
+      }
+      blocks.insert(start, block);
+    }
+
+    ControlFlowGraph {
+      self_modifications: self_modifications(&instructions, &invalid),
+      blocks,
+    }
+  }
+
+  /// The graph in Graphviz DOT, one box per block.
+  ///
+  /// Blocks whose code gets overwritten are drawn in red, and unknown jump
+  /// targets all lead to a single `?` node.
+  pub fn to_dot(&self) -> String {
+    let modified: BTreeSet<usize> = self
+      .self_modifications
+      .iter()
+      .map(|modification| modification.instruction)
+      .collect();
+
+    let mut dot = String::from("digraph intcode {\n  node [shape=box, fontname=\"monospace\"];\n");
+    let mut any_unknown = false;
+    for block in self.blocks.values() {
+      let mut label = String::new();
+      for (address, op_code) in &block.instructions {
+        let _ = write!(label, "{:04}: {}\\l", address, op_code);
+      }
+      if let Some(error) = &block.invalid {
+        let _ = write!(label, "{}\\l", error);
+      }
+      if block.instructions.is_empty() && block.invalid.is_none() {
+        let _ = write!(label, "{:04}: end of memory\\l", block.start);
+      }
+      let invalid_address = block
+        .instructions
+        .last()
+        .map_or(block.start, |(address, op_code)| address + op_code.size());
+      let is_modified = block
+        .instructions
+        .iter()
+        .any(|(address, _)| modified.contains(address))
+        || (block.invalid.is_some() && modified.contains(&invalid_address));
+      let colour = match is_modified {
+        true => ", color=red",
+        false => "",
+      };
+      let _ = writeln!(
+        dot,
+        "  b{} [label=\"{}\"{}];",
+        block.start,
+        label.replace('"', "\\\""),
+        colour
+      );
+
+      for edge in &block.edges {
+        let to = match edge.target {
+          Target::Block(target) => format!("b{}", target),
+          Target::Unknown => {
+            any_unknown = true;
+            "unknown".to_string()
+          }
+        };
+        let style = match edge.kind {
+          EdgeKind::FallThrough => "",
+          EdgeKind::Jump => " [label=\"jump\"]",
+          EdgeKind::Branch => " [label=\"branch\"]",
+        };
+        let _ = writeln!(dot, "  b{} -> {}{};", block.start, to, style);
+      }
+    }
+    if any_unknown {
+      dot.push_str("  unknown [label=\"?\", shape=circle, style=dashed];\n");
+    }
+    dot.push_str("}\n");
+    dot
+  }
+}
+
+/// Writes to fixed addresses that land inside any reachable instruction,
+/// including words that only decode once they've been written.
+fn self_modifications(
+  instructions: &BTreeMap<usize, OpCode>,
+  invalid: &BTreeMap<usize, ComputerError>,
+) -> Vec<SelfModification> {
+  let owner = |target: usize| {
+    if invalid.contains_key(&target) {
+      return Some(target);
+    }
+    instructions
+      .range(..=target)
+      .next_back()
+      .filter(|(address, op_code)| target < *address + op_code.size())
+      .map(|(address, _)| *address)
+  };
+
+  instructions
+    .iter()
+    .filter_map(|(&address, op_code)| match op_code.destination() {
+      Some(OpArg::Reference(target)) => {
+        let target = target.to_usize()?;
+        owner(target).map(|instruction| SelfModification {
+          address,
+          target,
+          instruction,
+        })
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  fn graph(program: &str) -> ControlFlowGraph {
+    ControlFlowGraph::build(&ComputerState::from(parse_op_stack(program).unwrap()))
+  }
+
+  #[test]
+  fn test_blocks_and_edges() {
+    // 0000: in -> @16
+    // 0002: jz @16, 12
+    // 0005: out 1
+    // 0007: jnz 1, 14
+    // 0010: out 0        never reached
+    // 0012: hlt
+    // 0013: hlt          never reached
+    // 0014: out 2
+    let graph = graph("3,16,1006,16,12,104,1,1105,1,14,104,0,99,99,104,2,0");
+    let starts: Vec<usize> = graph.blocks.keys().copied().collect();
+    assert_eq!(starts, vec![0, 5, 12, 14]);
+
+    assert_eq!(
+      graph.blocks[&0].edges,
+      vec![
+        Edge {
+          target: Target::Block(12),
+          kind: EdgeKind::Branch
+        },
+        Edge {
+          target: Target::Block(5),
+          kind: EdgeKind::FallThrough
+        },
+      ]
+    );
+    assert_eq!(graph.blocks[&0].instructions.len(), 2);
+    assert_eq!(
+      graph.blocks[&5].edges,
+      vec![Edge {
+        target: Target::Block(14),
+        kind: EdgeKind::Jump
+      }]
+    );
+    assert!(graph.blocks[&12].edges.is_empty());
+    // `out 2` then runs into the 0 at 16, which isn't an op code until
+    // the `in` writes there.
+    assert!(graph.blocks[&14].invalid.is_some());
+    assert_eq!(
+      graph.self_modifications,
+      vec![SelfModification {
+        address: 0,
+        target: 16,
+        instruction: 16
+      }]
+    );
+  }
+
+  #[test]
+  fn test_unknown_jumps() {
+    // jnz 1, @4, with the target read from memory.
+    let graph = graph("105,1,4,99,3");
+    assert_eq!(
+      graph.blocks[&0].edges[0],
+      Edge {
+        target: Target::Unknown,
+        kind: EdgeKind::Jump
+      }
+    );
+    let dot = graph.to_dot();
+    assert!(dot.contains("b0 -> unknown [label=\"jump\"];"));
+    assert!(dot.contains("unknown [label=\"?\""));
+  }
+
+  #[test]
+  fn test_self_modification() {
+    // Patches the mul at 4 into an add before running it.
+    let rewritten = graph("1101,0,1,4,2,9,9,9,99,0");
+    assert_eq!(
+      rewritten.self_modifications,
+      vec![SelfModification {
+        address: 0,
+        target: 4,
+        instruction: 4
+      }]
+    );
+    let dot = rewritten.to_dot();
+    assert!(dot.starts_with("digraph intcode {"));
+
+    // Builds the op code at 6 out of input, like day 5's diagnostics.
+    let patched = graph("3,9,1,9,6,6,1100,1,1,99");
+    assert_eq!(
+      patched.self_modifications,
+      vec![SelfModification {
+        address: 2,
+        target: 6,
+        instruction: 6
+      }]
+    );
+    assert!(patched.to_dot().contains(", color=red];"));
+    assert!(dot.contains(
+      "b0 [label=\"0000: add 0, 1 -> @4\\l0004: mul @9, @9 -> @9\\l0008: hlt\\l\", color=red];"
+    ));
+  }
+}
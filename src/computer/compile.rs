@@ -0,0 +1,352 @@
+use std::collections::BTreeSet;
+
+use num::traits::{CheckedAdd, CheckedMul, One, Zero};
+
+use super::cfg::ControlFlowGraph;
+use super::disassembler::disassemble;
+use super::{
+  Computer, ComputerError, ComputerState, ComputerWord, Instruction, InstructionSize, OpArg, OpCode,
+};
+
+/// An argument with its address worked out ahead of time where possible.
+#[derive(Clone)]
+enum Operand {
+  Literal(ComputerWord),
+  Position(usize),
+  Relative(ComputerWord),
+}
+
+impl Operand {
+  /// `None` for a negative address, which is left to the interpreter to report.
+  fn of(arg: &OpArg) -> Option<Operand> {
+    Some(match arg {
+      OpArg::Literal(value) => Operand::Literal(value.clone()),
+      OpArg::Reference(address) => Operand::Position(ComputerState::address(address).ok()?),
+      OpArg::Relative(offset) => Operand::Relative(offset.clone()),
+    })
+  }
+
+  fn read(&self, computer: &Computer) -> Result<ComputerWord, ComputerError> {
+    match self {
+      Operand::Literal(value) => Ok(value.clone()),
+      Operand::Position(address) => Ok(computer.state.get(*address)),
+      Operand::Relative(offset) => computer.resolve_relative(offset),
+    }
+  }
+
+  fn address(&self, computer: &Computer) -> Result<usize, ComputerError> {
+    match self {
+      Operand::Literal(_) => unreachable!("Destinations are never literals"),
+      Operand::Position(address) => Ok(*address),
+      Operand::Relative(offset) => computer.calc_relative(offset),
+    }
+  }
+}
+
+/// What to do after a compiled instruction.
+enum Flow {
+  Continue,
+  Halt,
+}
+
+/// A running compiled program: the computer, plus which compiled
+/// instructions have since been overwritten.
+struct Run<'a> {
+  computer: &'a mut Computer,
+  sizes: &'a [usize],
+  stale: Vec<bool>,
+}
+
+impl Run<'_> {
+  fn write(&mut self, address: usize, value: ComputerWord) {
+    self.computer.write(address, value);
+    self.mark_stale(address);
+  }
+
+  fn mark_stale(&mut self, address: usize) {
+    for start in address.saturating_sub(3)..=address {
+      if self
+        .sizes
+        .get(start)
+        .is_some_and(|size| start + size > address)
+      {
+        self.stale[start] = true;
+      }
+    }
+  }
+}
+
+type Step = Box<dyn Fn(&mut Run) -> Result<Flow, ComputerError> + Send + Sync>;
+
+fn binary(
+  address: usize,
+  args: [Operand; 3],
+  operation: fn(&ComputerWord, &ComputerWord) -> Option<ComputerWord>,
+) -> Step {
+  let [a, b, destination] = args;
+  Box::new(move |run| {
+    let result = operation(&a.read(run.computer)?, &b.read(run.computer)?)
+      .ok_or(ComputerError::Overflow { address })?;
+    let destination = destination.address(run.computer)?;
+    run.write(destination, result);
+    run.computer.instruction_pointer = address + 4;
+    Ok(Flow::Continue)
+  })
+}
+
+fn jump(address: usize, test: Operand, target: Operand, if_zero: bool) -> Step {
+  Box::new(move |run| {
+    run.computer.instruction_pointer = if test.read(run.computer)?.is_zero() == if_zero {
+      ComputerState::address(&target.read(run.computer)?)?
+    } else {
+      address + 3
+    };
+    Ok(Flow::Continue)
+  })
+}
+
+fn flag(condition: bool) -> Option<ComputerWord> {
+  Some(match condition {
+    true => ComputerWord::one(),
+    false => ComputerWord::zero(),
+  })
+}
+
+/// Turns one decoded instruction into a closure with its arguments bound.
+fn compile(address: usize, op_code: &OpCode) -> Option<Step> {
+  let args = op_code
+    .args()
+    .into_iter()
+    .map(Operand::of)
+    .collect::<Option<Vec<Operand>>>()?;
+  let arg = |index: usize| args[index].clone();
+
+  Some(match op_code {
+    OpCode::Add(_) => binary(address, [arg(0), arg(1), arg(2)], |a, b| {
+      CheckedAdd::checked_add(a, b)
+    }),
+    OpCode::Mul(_) => binary(address, [arg(0), arg(1), arg(2)], |a, b| {
+      CheckedMul::checked_mul(a, b)
+    }),
+    OpCode::LessThan(_) => binary(address, [arg(0), arg(1), arg(2)], |a, b| flag(a < b)),
+    OpCode::Equals(_) => binary(address, [arg(0), arg(1), arg(2)], |a, b| flag(a == b)),
+    OpCode::JumpIfNonZero(_) => jump(address, arg(0), arg(1), false),
+    OpCode::JumpIfZero(_) => jump(address, arg(0), arg(1), true),
+    OpCode::ReadInput { .. } => {
+      let destination = arg(0);
+      Box::new(move |run| {
+        let input = run
+          .computer
+          .input
+          .pop_front()
+          .ok_or(ComputerError::MissingInput { address })?;
+        let destination = destination.address(run.computer)?;
+        run.write(destination, input);
+        run.computer.instruction_pointer = address + 2;
+        Ok(Flow::Continue)
+      })
+    }
+    OpCode::SaveOutput { .. } => {
+      let source = arg(0);
+      Box::new(move |run| {
+        let value = source.read(run.computer)?;
+        run.computer.output.push(value);
+        run.computer.instruction_pointer = address + 2;
+        Ok(Flow::Continue)
+      })
+    }
+    OpCode::RelativeAdjustment(_) => {
+      let adjustment = arg(0);
+      Box::new(move |run| {
+        let adjustment = adjustment.read(run.computer)?;
+        run.computer.relative_base =
+          CheckedAdd::checked_add(&run.computer.relative_base, &adjustment)
+            .ok_or(ComputerError::Overflow { address })?;
+        run.computer.instruction_pointer = address + 2;
+        Ok(Flow::Continue)
+      })
+    }
+    OpCode::Done => Box::new(|_| Ok(Flow::Halt)),
+  })
+}
+
+/// A program translated ahead of time into a chain of closures, one per
+/// instruction, with their arguments already decoded.
+///
+/// Every instruction found by a linear sweep or by following the program's
+/// control flow is compiled, apart from those the program is seen to
+/// overwrite. Anything else, and any compiled instruction whose words get
+/// written while running, falls back to the interpreter.
+pub struct Compiled {
+  program: Vec<ComputerWord>,
+  steps: Vec<Option<Step>>,
+  /// The size of the compiled instruction at each address, or zero.
+  sizes: Vec<usize>,
+}
+
+impl Compiled {
+  pub fn new(program: Vec<ComputerWord>) -> Self {
+    let state = ComputerState::from(program.clone());
+    let graph = ControlFlowGraph::build(&state);
+    let modified: BTreeSet<usize> = graph
+      .self_modifications
+      .iter()
+      .map(|modification| modification.instruction)
+      .collect();
+
+    let mut addresses: BTreeSet<usize> = disassemble(&state)
+      .into_iter()
+      .filter(|line| line.op_code.is_some())
+      .map(|line| line.address)
+      .collect();
+    addresses.extend(
+      graph
+        .blocks
+        .values()
+        .flat_map(|block| block.instructions.iter().map(|(address, _)| *address)),
+    );
+
+    let mut steps: Vec<Option<Step>> = (0..state.len()).map(|_| None).collect();
+    let mut sizes = vec![0; state.len()];
+    for address in addresses.difference(&modified).copied() {
+      if let Ok(op_code) = Instruction::at(&state, address).decode() {
+        if let Some(step) = compile(address, &op_code) {
+          steps[address] = Some(step);
+          sizes[address] = op_code.size();
+        }
+      }
+    }
+
+    Compiled {
+      program,
+      steps,
+      sizes,
+    }
+  }
+
+  /// How many instructions were compiled.
+  pub fn len(&self) -> usize {
+    self.steps.iter().filter(|step| step.is_some()).count()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// A fresh computer loaded with the program, to feed input to before [`Compiled::run`].
+  pub fn computer(&self) -> Computer {
+    Computer::new(self.program.clone())
+  }
+
+  /// Runs `computer` to completion like [`Computer::run`], returning its output.
+  ///
+  /// `computer` needn't be fresh from [`Compiled::computer`]: compiled
+  /// instructions whose words no longer match the program, because it has
+  /// been patched, restored or has already run, are interpreted instead.
+  /// Traced or profiled computers are interpreted throughout, so that every
+  /// instruction is seen.
+  pub fn run(&self, mut computer: Computer) -> Result<Vec<ComputerWord>, ComputerError> {
+    if computer.trace.is_some() || computer.profile.is_some() {
+      return computer.run();
+    }
+
+    let mut run = Run {
+      computer: &mut computer,
+      sizes: &self.sizes,
+      stale: vec![false; self.sizes.len()],
+    };
+    for (address, word) in self.program.iter().enumerate() {
+      if run.computer.state.get(address) != *word {
+        run.mark_stale(address);
+      }
+    }
+    loop {
+      let address = run.computer.instruction_pointer;
+      if let Some(Some(step)) = self.steps.get(address) {
+        if !run.stale[address] {
+          match step(&mut run)? {
+            Flow::Continue => continue,
+            Flow::Halt => break,
+          }
+        }
+      }
+
+      let op_code = match run.computer.fetch()? {
+        Some(OpCode::Done) | None => break,
+        Some(op_code) => op_code,
+      };
+      let destination = match op_code.destination() {
+        Some(destination) => run.computer.address_of(destination)?,
+        None => None,
+      };
+      run.computer.execute(&op_code)?;
+      if let Some(destination) = destination {
+        run.mark_stale(destination);
+      }
+    }
+
+    Ok(computer.output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  fn compare(program: &str, inputs: &[i64]) {
+    let program = parse_op_stack(program).unwrap();
+    let compiled = Compiled::new(program.clone());
+    let mut computer = compiled.computer();
+    let mut interpreted = Computer::new(program);
+    for input in inputs {
+      computer.push_input(ComputerWord::from(*input));
+      interpreted.push_input(ComputerWord::from(*input));
+    }
+    assert_eq!(compiled.run(computer), interpreted.run());
+  }
+
+  #[test]
+  fn test_matches_interpreter_on_day05() {
+    let program = include_str!("../days/resources/day05.txt");
+    compare(program, &[1]);
+    compare(program, &[5]);
+  }
+
+  #[test]
+  fn test_matches_interpreter_on_day09() {
+    let program = include_str!("../days/resources/day09.txt");
+    compare(program, &[1]);
+    compare(program, &[2]);
+    compare(
+      "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+      &[],
+    );
+  }
+
+  #[test]
+  fn test_overwritten_code_falls_back() {
+    // The relative `in` writes over the literal in the `out` at 4, which
+    // can't be spotted ahead of time so gets compiled.
+    let program = "109,1,203,4,104,5,99";
+    let compiled = Compiled::new(parse_op_stack(program).unwrap());
+    assert!(compiled.steps[4].is_some());
+    let computer = compiled.computer().add_input(42);
+    assert_eq!(compiled.run(computer), Ok(vec![ComputerWord::from(42)]));
+    compare(program, &[42]);
+    compare(program, &[]);
+  }
+
+  #[test]
+  fn test_changed_computers_fall_back() {
+    let compiled = Compiled::new(parse_op_stack("104,5,99").unwrap());
+    let patched = Computer::new(parse_op_stack("104,7,99").unwrap());
+    assert_eq!(compiled.run(patched), Ok(vec![ComputerWord::from(7)]));
+
+    // Stepped past the `in`, which overwrites the literal in the `out`.
+    let compiled = Compiled::new(parse_op_stack("3,3,104,5,99").unwrap());
+    let mut computer = compiled.computer().add_input(9);
+    computer.step().unwrap();
+    assert_eq!(compiled.run(computer), Ok(vec![ComputerWord::from(9)]));
+  }
+}
@@ -0,0 +1,459 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::trace::{Trace, Watch, WatchHit};
+use super::{Computer, ComputerError, ComputerWord, OpCode, Status};
+
+/// Why the debugger handed control back.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Stop {
+  /// A single step finished and the program can keep going.
+  Stepped,
+  Breakpoint(usize),
+  Watchpoint(WatchHit),
+  NeedsInput,
+  Halted,
+}
+
+/// Wraps a [`Computer`] with breakpoints, single-stepping and memory access.
+///
+/// Output produced while stepping is collected rather than returned, see
+/// [`Debugger::output`].
+pub struct Debugger {
+  computer: Computer,
+  breakpoints: BTreeSet<usize>,
+  output: Vec<ComputerWord>,
+}
+
+impl Debugger {
+  pub fn new(computer: Computer) -> Self {
+    Debugger {
+      computer,
+      breakpoints: BTreeSet::new(),
+      output: Vec::new(),
+    }
+  }
+
+  pub fn add_breakpoint(&mut self, address: usize) {
+    self.breakpoints.insert(address);
+  }
+
+  pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+    self.breakpoints.remove(&address)
+  }
+
+  pub fn breakpoints(&self) -> impl Iterator<Item = &usize> {
+    self.breakpoints.iter()
+  }
+
+  /// Stops execution whenever `address` is accessed in a way `kind` covers.
+  pub fn watch(&mut self, address: usize, kind: Watch) {
+    if self.computer.trace.is_none() {
+      self.computer.trace = Some(Trace::watch_only());
+    }
+    if let Some(trace) = self.computer.trace_mut() {
+      trace.watch(address, kind);
+    }
+  }
+
+  pub fn unwatch(&mut self, address: usize) -> bool {
+    self
+      .computer
+      .trace_mut()
+      .is_some_and(|trace| trace.unwatch(address))
+  }
+
+  fn watch_hits(&self) -> &[WatchHit] {
+    self.computer.trace().map_or(&[], |trace| trace.hits())
+  }
+
+  pub fn instruction_pointer(&self) -> usize {
+    self.computer.instruction_pointer
+  }
+
+  pub fn relative_base(&self) -> ComputerWord {
+    self.computer.relative_base.clone()
+  }
+
+  /// Input that hasn't been read yet, next one first.
+  pub fn input(&self) -> impl Iterator<Item = &ComputerWord> {
+    self.computer.input.iter()
+  }
+
+  pub fn push_input(&mut self, value: ComputerWord) {
+    self.computer.push_input(value)
+  }
+
+  /// Everything the program has output so far.
+  pub fn output(&self) -> &[ComputerWord] {
+    &self.output
+  }
+
+  pub fn peek(&self, address: usize) -> ComputerWord {
+    self.computer.resolve(address)
+  }
+
+  pub fn poke(&mut self, address: usize, value: ComputerWord) {
+    self.computer.write(address, value)
+  }
+
+  /// The machine as a binary memory dump, see [`Computer::snapshot_bytes`],
+  /// with the output collected so far.
+  pub fn dump(&self) -> Vec<u8> {
+    let mut computer = self.computer.clone();
+    computer.output = self.output.clone();
+    computer.snapshot_bytes()
+  }
+
+  /// Carries on from a [`Debugger::dump`], keeping the breakpoints and
+  /// watchpoints already set.
+  pub fn load(&mut self, dump: &[u8]) -> Result<(), ComputerError> {
+    let mut computer = Computer::restore_bytes(dump)?;
+    self.output = std::mem::take(&mut computer.output);
+    computer.trace = self.computer.trace.take();
+    self.computer = computer;
+    Ok(())
+  }
+
+  /// The decoded instruction at the instruction pointer.
+  pub fn current(&self) -> Result<Option<OpCode>, ComputerError> {
+    self.computer.next()
+  }
+
+  pub fn step(&mut self) -> Result<Stop, ComputerError> {
+    let seen_hits = self.watch_hits().len();
+    let stepped = match self.computer.step()? {
+      None => Stop::Stepped,
+      Some(Status::Output(value)) => {
+        self.output.push(value);
+        Stop::Stepped
+      }
+      Some(Status::NeedsInput) => return Ok(Stop::NeedsInput),
+      Some(Status::Halted) => return Ok(Stop::Halted),
+    };
+
+    match self.watch_hits().get(seen_hits) {
+      Some(hit) => Ok(Stop::Watchpoint(hit.clone())),
+      None => Ok(stepped),
+    }
+  }
+
+  /// Runs until a breakpoint or watchpoint is reached, the program needs input, or it halts.
+  ///
+  /// The instruction at the current address always runs, so continuing from a
+  /// breakpoint doesn't immediately stop on it again.
+  pub fn resume(&mut self) -> Result<Stop, ComputerError> {
+    loop {
+      match self.step()? {
+        Stop::Stepped => {
+          let address = self.instruction_pointer();
+          if self.breakpoints.contains(&address) {
+            return Ok(Stop::Breakpoint(address));
+          }
+        }
+        stop => return Ok(stop),
+      }
+    }
+  }
+
+  pub fn into_computer(self) -> Computer {
+    self.computer
+  }
+}
+
+const HELP: &str = "\
+commands:
+  s, step [n]          execute n instructions (default 1)
+  c, continue          run until a breakpoint, input is needed or the program halts
+  b, break <addr>      set a breakpoint
+  d, delete <addr>     remove a breakpoint
+  w, watch <addr> [r|w|rw]
+                       stop when an address is read and/or written (default rw)
+  unwatch <addr>       remove a watchpoint
+  x, peek <addr> [n]   show n words of memory (default 1, at most 1024)
+  poke <addr> <value>  write a word of memory
+  i, input <values>    queue input values
+  r, regs              show the instruction pointer, relative base and queues
+  l, list              show the current instruction
+  dump <path>          save the machine to a binary memory dump
+  load <path>          restore the machine from a memory dump
+  q, quit              leave the debugger";
+
+/// The most words `peek` shows at once.
+const MAX_PEEK: usize = 1024;
+
+fn parse_args<T: FromStr>(args: &[&str]) -> Result<Vec<T>, String> {
+  args
+    .iter()
+    .map(|arg| T::from_str(arg).map_err(|_| format!("Invalid argument '{}'", arg)))
+    .collect()
+}
+
+fn join<'a>(words: impl Iterator<Item = &'a ComputerWord>) -> String {
+  let words: Vec<String> = words.map(|w| w.to_string()).collect();
+  words.join(",")
+}
+
+fn describe_current(debugger: &Debugger) -> String {
+  let address = debugger.instruction_pointer();
+  match debugger.current() {
+    Ok(Some(op_code)) => format!("{:04}: {}", address, op_code),
+    Ok(None) => format!("{:04}: <end>", address),
+    Err(error) => format!("{:04}: {}", address, error),
+  }
+}
+
+fn describe_stop(debugger: &Debugger, stop: Result<Stop, ComputerError>) -> String {
+  let reason = match stop {
+    Ok(Stop::Stepped) => return describe_current(debugger),
+    Ok(Stop::Breakpoint(address)) => format!("breakpoint @{}", address),
+    Ok(Stop::Watchpoint(hit)) => format!(
+      "watchpoint: {} @{} = {}",
+      match hit.kind {
+        Watch::Read => "read",
+        _ => "write",
+      },
+      hit.access.address,
+      hit.access.value
+    ),
+    Ok(Stop::NeedsInput) => "waiting for input".to_string(),
+    Ok(Stop::Halted) => "halted".to_string(),
+    Err(error) => format!("error: {}", error),
+  };
+  format!("{}\n{}", reason, describe_current(debugger))
+}
+
+/// Runs one REPL command, returning the text to show or a usage error.
+fn command(debugger: &mut Debugger, command: &str, args: &[&str]) -> Result<String, String> {
+  match command {
+    "s" | "step" => parse_args::<usize>(args).map(|count| {
+      let count = count.first().cloned().unwrap_or(1);
+      let mut stop = Ok(Stop::Stepped);
+      for _ in 0..count {
+        stop = debugger.step();
+        if stop != Ok(Stop::Stepped) {
+          break;
+        }
+      }
+      describe_stop(debugger, stop)
+    }),
+    "c" | "continue" => {
+      let stop = debugger.resume();
+      Ok(describe_stop(debugger, stop))
+    }
+    "b" | "break" | "d" | "delete" => parse_args::<usize>(args).map(|addresses| {
+      for address in addresses {
+        if command.starts_with('b') {
+          debugger.add_breakpoint(address);
+        } else {
+          debugger.remove_breakpoint(address);
+        }
+      }
+      let breakpoints: Vec<String> = debugger.breakpoints().map(|b| b.to_string()).collect();
+      format!("breakpoints: {}", breakpoints.join(", "))
+    }),
+    "w" | "watch" => {
+      let (address, kind) = match args {
+        [address] => (address, Watch::ReadWrite),
+        [address, "r"] => (address, Watch::Read),
+        [address, "w"] => (address, Watch::Write),
+        [address, "rw"] => (address, Watch::ReadWrite),
+        _ => return Err("usage: watch <addr> [r|w|rw]".to_string()),
+      };
+      let address = parse_args::<usize>(&[address])?[0];
+      debugger.watch(address, kind);
+      Ok(format!("watching @{}", address))
+    }
+    "unwatch" => parse_args::<usize>(args).map(|addresses| {
+      for &address in &addresses {
+        debugger.unwatch(address);
+      }
+      format!("stopped watching {:?}", addresses)
+    }),
+    "x" | "peek" => match parse_args::<usize>(args)?.as_slice() {
+      [address] => Ok(format!("{:04}: {}", address, debugger.peek(*address))),
+      [address, count @ 1..=MAX_PEEK] if address.checked_add(*count).is_some() => {
+        let lines: Vec<String> = (*address..address + count)
+          .map(|address| format!("{:04}: {}", address, debugger.peek(address)))
+          .collect();
+        Ok(lines.join("\n"))
+      }
+      _ => Err(format!("usage: peek <addr> [n], n from 1 to {}", MAX_PEEK)),
+    },
+    "poke" => match args {
+      [address, value] => {
+        let address = parse_args::<usize>(&[address])?[0];
+        let value = parse_args::<ComputerWord>(&[value])?.remove(0);
+        let reply = format!("{:04}: {}", address, value);
+        debugger.poke(address, value);
+        Ok(reply)
+      }
+      _ => Err("usage: poke <addr> <value>".to_string()),
+    },
+    "i" | "input" => parse_args::<ComputerWord>(args).map(|values| {
+      values
+        .into_iter()
+        .for_each(|value| debugger.push_input(value));
+      format!("input: [{}]", join(debugger.input()))
+    }),
+    "r" | "regs" => Ok(format!(
+      "ip: {}\nrelative base: {}\ninput: [{}]\noutput: [{}]",
+      debugger.instruction_pointer(),
+      debugger.relative_base(),
+      join(debugger.input()),
+      join(debugger.output().iter())
+    )),
+    "l" | "list" => Ok(describe_current(debugger)),
+    "dump" => match args {
+      [path] => {
+        let dump = debugger.dump();
+        fs::write(path, &dump)
+          .map(|_| format!("wrote {} bytes to {}", dump.len(), path))
+          .map_err(|error| format!("{}: {}", path, error))
+      }
+      _ => Err("usage: dump <path>".to_string()),
+    },
+    "load" => match args {
+      [path] => {
+        let dump = fs::read(path).map_err(|error| format!("{}: {}", path, error))?;
+        debugger
+          .load(&dump)
+          .map_err(|error| format!("{}: {}", path, error))?;
+        Ok(describe_current(debugger))
+      }
+      _ => Err("usage: load <path>".to_string()),
+    },
+    "h" | "help" => Ok(HELP.to_string()),
+    unknown => Err(format!("Unknown command '{}', try 'help'", unknown)),
+  }
+}
+
+/// Runs the debugger's command loop, reading commands from `input` until
+/// `quit` or end of input.
+pub fn repl(debugger: &mut Debugger, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+  writeln!(out, "{}", describe_current(debugger))?;
+  write!(out, "> ")?;
+  out.flush()?;
+
+  for line in input.lines() {
+    let line = line?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.split_first() {
+      Some((&"q", _)) | Some((&"quit", _)) => return Ok(()),
+      Some((name, args)) => match command(debugger, name, args) {
+        Ok(text) | Err(text) => writeln!(out, "{}", text)?,
+      },
+      None => {}
+    }
+    write!(out, "> ")?;
+    out.flush()?;
+  }
+
+  Ok(())
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::{from, ECHO};
+
+  #[test]
+  fn test_breakpoints() {
+    let mut debugger = Debugger::new(from(ECHO).unwrap().add_input(5).add_input(0));
+    debugger.add_breakpoint(4);
+
+    assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(4)));
+    assert_eq!(debugger.output(), &[5]);
+    assert_eq!(
+      debugger.current().unwrap().unwrap().to_string(),
+      "jnz @9, 0"
+    );
+
+    assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(4)));
+    assert_eq!(debugger.output(), &[5, 0]);
+
+    assert_eq!(debugger.resume(), Ok(Stop::Halted));
+    assert_eq!(debugger.instruction_pointer(), 7);
+  }
+
+  #[test]
+  fn test_watchpoints() {
+    let mut debugger = Debugger::new(from(ECHO).unwrap().add_input(5).add_input(0));
+    debugger.watch(9, Watch::Write);
+
+    match debugger.resume() {
+      Ok(Stop::Watchpoint(hit)) => {
+        assert_eq!((hit.instruction, hit.kind), (0, Watch::Write));
+        assert_eq!((hit.access.address, hit.access.value), (9, 5));
+      }
+      stop => panic!("Unexpected stop {:?}", stop),
+    }
+    assert_eq!(debugger.instruction_pointer(), 2);
+
+    assert!(debugger.unwatch(9));
+    assert_eq!(debugger.resume(), Ok(Stop::Halted));
+    assert_eq!(debugger.output(), &[5, 0]);
+  }
+
+  #[test]
+  fn test_step_and_patch() {
+    let mut debugger = Debugger::new(from(ECHO).unwrap());
+    assert_eq!(debugger.step(), Ok(Stop::NeedsInput));
+
+    debugger.push_input(3);
+    assert_eq!(debugger.step(), Ok(Stop::Stepped));
+    assert_eq!(debugger.peek(9), 3);
+
+    debugger.poke(9, 12);
+    assert_eq!(debugger.step(), Ok(Stop::Stepped));
+    assert_eq!(debugger.output(), &[12]);
+    assert_eq!(debugger.instruction_pointer(), 4);
+  }
+
+  #[test]
+  fn test_repl() {
+    let mut debugger = Debugger::new(from(ECHO).unwrap());
+    let commands = "input 8 0\nbreak 4\nc\nregs\npoke 9 1\nx 9\nx 9 0\n\
+                    x 18446744073709551615 2\nstep 2\nbogus\nd 4\nc\nq\nignored\n";
+    let mut out = Vec::new();
+
+    repl(&mut debugger, commands.as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    assert!(out.contains("breakpoint @4\n0004: jnz @9, 0"));
+    assert!(out.contains("ip: 4\nrelative base: 0\ninput: [0]\noutput: [8]"));
+    assert!(out.contains("0009: 1"));
+    assert_eq!(
+      out
+        .matches("usage: peek <addr> [n], n from 1 to 1024")
+        .count(),
+      2
+    );
+    assert!(out.contains("Unknown command 'bogus'"));
+    assert!(out.contains("0002: out @9"));
+    assert!(out.contains("halted\n0007: hlt"));
+    assert_eq!(debugger.output(), &[8, 0]);
+  }
+
+  #[test]
+  fn test_dump_and_load() {
+    let mut debugger = Debugger::new(from(ECHO).unwrap().add_input(5).add_input(0));
+    debugger.add_breakpoint(4);
+    assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(4)));
+    let dump = debugger.dump();
+
+    assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(4)));
+    assert_eq!(debugger.output(), &[5, 0]);
+
+    debugger.load(&dump).unwrap();
+    assert_eq!(debugger.output(), &[5]);
+    assert_eq!(debugger.input().collect::<Vec<_>>(), vec![&0]);
+    assert_eq!(debugger.resume(), Ok(Stop::Breakpoint(4)));
+    assert_eq!(debugger.resume(), Ok(Stop::Halted));
+    assert_eq!(debugger.output(), &[5, 0]);
+
+    assert!(debugger.load(b"1,2,3").is_err());
+  }
+}
@@ -0,0 +1,95 @@
+use std::fmt::{Display, Error, Formatter};
+
+use super::{ComputerState, ComputerWord, Instruction, InstructionSize, OpCode};
+
+/// One line of a listing: either a decoded instruction or a word that
+/// didn't decode and is shown as `data`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Line {
+  pub address: usize,
+  pub words: Vec<ComputerWord>,
+  pub op_code: Option<OpCode>,
+}
+
+impl Display for Line {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    let text = match &self.op_code {
+      Some(op_code) => op_code.to_string(),
+      None => format!("data {}", self.words[0]),
+    };
+    let raw: Vec<String> = self.words.iter().map(|w| w.to_string()).collect();
+    write!(f, "{:04}: {:<28} ; {}", self.address, text, raw.join(","))
+  }
+}
+
+/// Linear sweep over `state`, decoding from address zero to the end of memory.
+///
+/// Anything that isn't a valid instruction, or would run past the end of
+/// memory, becomes a single word of `data` and decoding resumes at the next address.
+pub fn disassemble(state: &ComputerState) -> Vec<Line> {
+  let mut lines = Vec::new();
+  let mut address = 0;
+
+  while address < state.len() {
+    let op_code = Instruction::at(state, address)
+      .decode()
+      .ok()
+      .filter(|op_code| address + op_code.size() <= state.len());
+    let size = op_code.as_ref().map_or(1, |op_code| op_code.size());
+
+    lines.push(Line {
+      address,
+      words: (address..address + size).map(|a| state.get(a)).collect(),
+      op_code,
+    });
+    address += size;
+  }
+
+  lines
+}
+
+/// The whole program as text, one [`Line`] per line.
+pub fn listing(state: &ComputerState) -> String {
+  disassemble(state)
+    .iter()
+    .map(|line| format!("{}\n", line))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  fn state(program: &str) -> ComputerState {
+    ComputerState::from(parse_op_stack(program).unwrap())
+  }
+
+  #[test]
+  fn test_listing() {
+    let listing = listing(&state(
+      "1002,4,3,4,33,21001,4,3,2,109,-1,3,7,204,0,1105,1,0,99",
+    ));
+    let lines: Vec<&str> = listing.lines().map(|l| l.trim_end()).collect();
+    assert_eq!(
+      lines,
+      vec![
+        "0000: mul @4, 3 -> @4              ; 1002,4,3,4",
+        "0004: data 33                      ; 33",
+        "0005: add @4, 3 -> r2              ; 21001,4,3,2",
+        "0009: arb -1                       ; 109,-1",
+        "0011: in -> @7                     ; 3,7",
+        "0013: out r0                       ; 204,0",
+        "0015: jnz 1, 0                     ; 1105,1,0",
+        "0018: hlt                          ; 99",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_truncated_instruction_is_data() {
+    let lines = disassemble(&state("1,0"));
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| line.op_code.is_none()));
+  }
+}
@@ -0,0 +1,79 @@
+use std::fmt::{Display, Error, Formatter};
+
+use super::ComputerWord;
+
+/// Everything that can go wrong parsing or running an Intcode program.
+///
+/// Addresses are those of the offending instruction.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ComputerError {
+  InvalidOpCode {
+    op_code: ComputerWord,
+    address: usize,
+  },
+  InvalidParameterMode {
+    mode: ComputerWord,
+    address: usize,
+  },
+  WriteToImmediate {
+    address: usize,
+  },
+  NegativeAddress(ComputerWord),
+  /// Memory ends just before `usize::MAX`, so its length always fits in a `usize`.
+  AddressOutOfRange(ComputerWord),
+  /// Only raised for `i64` words; with the `bigint` feature arithmetic can't overflow.
+  Overflow {
+    address: usize,
+  },
+  MissingInput {
+    address: usize,
+  },
+  /// `position` is the index of the token in the comma-separated program.
+  Parse {
+    token: String,
+    position: usize,
+  },
+  /// `line` is 1-based.
+  InvalidSnapshot {
+    line: usize,
+    message: String,
+  },
+  /// `offset` is the byte the problem starts at.
+  InvalidBinary {
+    offset: usize,
+    message: String,
+  },
+}
+
+impl Display for ComputerError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    match self {
+      ComputerError::InvalidOpCode { op_code, address } => {
+        write!(f, "Invalid opcode: {} @{}", op_code, address)
+      }
+      ComputerError::InvalidParameterMode { mode, address } => {
+        write!(f, "Invalid parameter mode: {} @{}", mode, address)
+      }
+      ComputerError::WriteToImmediate { address } => {
+        write!(f, "Write to immediate parameter @{}", address)
+      }
+      ComputerError::NegativeAddress(address) => write!(f, "Negative address: {}", address),
+      ComputerError::AddressOutOfRange(address) => {
+        write!(f, "Address out of range: {}", address)
+      }
+      ComputerError::Overflow { address } => write!(f, "Arithmetic overflow @{}", address),
+      ComputerError::MissingInput { address } => write!(f, "No Input @{}", address),
+      ComputerError::Parse { token, position } => {
+        write!(f, "Can't parse '{}' at position {}", token, position)
+      }
+      ComputerError::InvalidSnapshot { line, message } => {
+        write!(f, "Invalid snapshot on line {}: {}", line, message)
+      }
+      ComputerError::InvalidBinary { offset, message } => {
+        write!(f, "Invalid binary at byte {}: {}", offset, message)
+      }
+    }
+  }
+}
+
+impl std::error::Error for ComputerError {}
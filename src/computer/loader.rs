@@ -0,0 +1,174 @@
+use std::fmt::{Display, Error, Formatter};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::binary;
+use super::{ComputerError, ComputerWord};
+
+/// Why a program couldn't be loaded. Lines and columns start at 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LoadError {
+  Io(String),
+  /// A program in the binary format that doesn't decode.
+  Binary(ComputerError),
+  Syntax {
+    line: usize,
+    column: usize,
+    message: String,
+  },
+}
+
+impl Display for LoadError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    match self {
+      LoadError::Io(message) => write!(f, "{}", message),
+      LoadError::Binary(error) => write!(f, "{}", error),
+      LoadError::Syntax {
+        line,
+        column,
+        message,
+      } => write!(f, "{}:{}: {}", line, column, message),
+    }
+  }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Parses a program written as comma separated words.
+///
+/// Unlike [`super::parse_op_stack`] this takes the program over as many
+/// lines as it likes, with `;` comments, and tolerates whitespace and a
+/// trailing comma.
+pub fn parse(text: &str) -> Result<Vec<ComputerWord>, LoadError> {
+  let mut words = Vec::new();
+  // Whether a word has been read since the last comma.
+  let mut separated = false;
+  for (index, line) in text.lines().enumerate() {
+    let code = line.split(';').next().unwrap_or("");
+    let error = |start: usize, message: String| LoadError::Syntax {
+      line: index + 1,
+      column: code[..start].chars().count() + 1,
+      message,
+    };
+
+    let mut chars = code.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+      if c.is_whitespace() {
+        continue;
+      }
+      if c == ',' {
+        if !separated {
+          return Err(error(start, "Expected a word before ','".to_string()));
+        }
+        separated = false;
+        continue;
+      }
+
+      let mut end = start + c.len_utf8();
+      while let Some(&(next, c)) = chars.peek() {
+        if c == ',' || c.is_whitespace() {
+          break;
+        }
+        end = next + c.len_utf8();
+        chars.next();
+      }
+      let token = &code[start..end];
+      let word = ComputerWord::from_str(token)
+        .map_err(|_| error(start, format!("Can't parse '{}'", token)))?;
+      words.push(word);
+      separated = true;
+    }
+  }
+  Ok(words)
+}
+
+/// Reads a whole program from `reader`, either in the binary format or
+/// as text, which [`parse`] reads.
+pub fn read(mut reader: impl Read) -> Result<Vec<ComputerWord>, LoadError> {
+  let mut bytes = Vec::new();
+  reader
+    .read_to_end(&mut bytes)
+    .map_err(|error| LoadError::Io(error.to_string()))?;
+  from_bytes(&bytes)
+}
+
+/// Reads the program in the file at `path`, as [`read`] does.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<ComputerWord>, LoadError> {
+  let bytes = fs::read(path).map_err(|error| LoadError::Io(error.to_string()))?;
+  from_bytes(&bytes)
+}
+
+fn from_bytes(bytes: &[u8]) -> Result<Vec<ComputerWord>, LoadError> {
+  if binary::is_binary(bytes) {
+    return binary::decode(bytes).map_err(LoadError::Binary);
+  }
+  let text = std::str::from_utf8(bytes)
+    .map_err(|_| LoadError::Io("stream did not contain valid UTF-8".to_string()))?;
+  parse(text)
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+
+  fn syntax(line: usize, column: usize, message: &str) -> LoadError {
+    LoadError::Syntax {
+      line,
+      column,
+      message: message.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(parse("1,0,0,0,99\n\n"), Ok(vec![1, 0, 0, 0, 99]));
+    assert_eq!(
+      parse("; Outputs its input.\n  3, 5,   ; in -> @5\n4,5,\n99,\n"),
+      Ok(vec![3, 5, 4, 5, 99])
+    );
+    assert_eq!(parse(""), Ok(vec![]));
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(
+      parse("1,0,\n0,x0,99"),
+      Err(syntax(2, 3, "Can't parse 'x0'"))
+    );
+    assert_eq!(
+      parse("1,,2"),
+      Err(syntax(1, 3, "Expected a word before ','"))
+    );
+    assert_eq!(
+      parse("  ,1"),
+      Err(syntax(1, 3, "Expected a word before ','"))
+    );
+    assert_eq!(
+      parse("1,2\n3,4\n5,6é,7").unwrap_err().to_string(),
+      "3:3: Can't parse '6é'"
+    );
+  }
+
+  #[test]
+  fn test_read_and_load() {
+    assert_eq!(read("104,7,99\n".as_bytes()), Ok(vec![104, 7, 99]));
+    assert_eq!(
+      read(&binary::encode(&[104, 7, 99], true)[..]),
+      Ok(vec![104, 7, 99])
+    );
+    assert!(matches!(
+      read(&b"INTC\x09"[..]),
+      Err(LoadError::Binary(ComputerError::InvalidBinary {
+        offset: 4,
+        ..
+      }))
+    ));
+    assert_eq!(
+      load("src/days/resources/day05.txt").map(|program| program[..2].to_vec()),
+      Ok(vec![3, 225])
+    );
+    assert!(matches!(load("no/such/program.txt"), Err(LoadError::Io(_))));
+  }
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+use super::{Computer, ComputerError, ComputerWord, Status};
+
+/// Where a [`Machine`] takes its input from, once the computer's own input
+/// queue is empty.
+pub trait Input {
+  /// The next word, blocking until there is one if need be, or `None` if
+  /// there won't be any more.
+  fn read(&mut self) -> Option<ComputerWord>;
+}
+
+/// Where a [`Machine`] sends its output.
+pub trait Output {
+  fn write(&mut self, value: ComputerWord);
+}
+
+impl Input for VecDeque<ComputerWord> {
+  fn read(&mut self) -> Option<ComputerWord> {
+    self.pop_front()
+  }
+}
+
+impl Output for VecDeque<ComputerWord> {
+  fn write(&mut self, value: ComputerWord) {
+    self.push_back(value);
+  }
+}
+
+impl Output for Vec<ComputerWord> {
+  fn write(&mut self, value: ComputerWord) {
+    self.push(value);
+  }
+}
+
+/// Blocks until another thread sends a word, and runs dry once every sender
+/// is gone.
+impl Input for Receiver<ComputerWord> {
+  fn read(&mut self) -> Option<ComputerWord> {
+    self.recv().ok()
+  }
+}
+
+/// Output sent after the receiver has gone is dropped.
+impl Output for Sender<ComputerWord> {
+  fn write(&mut self, value: ComputerWord) {
+    let _ = self.send(value);
+  }
+}
+
+impl Output for SyncSender<ComputerWord> {
+  fn write(&mut self, value: ComputerWord) {
+    let _ = self.send(value);
+  }
+}
+
+impl<F: FnMut() -> Option<ComputerWord>> Input for F {
+  fn read(&mut self) -> Option<ComputerWord> {
+    self()
+  }
+}
+
+impl<F: FnMut(ComputerWord)> Output for F {
+  fn write(&mut self, value: ComputerWord) {
+    self(value)
+  }
+}
+
+/// A computer wired up to an input and an output, so it can run to the end
+/// on its own; on a thread of its own with channels, say, to talk to other
+/// machines.
+pub struct Machine<I, O> {
+  computer: Computer,
+  input: I,
+  output: O,
+}
+
+impl<I: Input, O: Output> Machine<I, O> {
+  pub fn new(computer: Computer, input: I, output: O) -> Self {
+    Machine {
+      computer,
+      input,
+      output,
+    }
+  }
+
+  /// Runs until the program halts.
+  ///
+  /// Fails with [`ComputerError::MissingInput`] if the program reads once
+  /// its input has run dry.
+  pub fn run(&mut self) -> Result<(), ComputerError> {
+    loop {
+      match self.computer.resume()? {
+        Status::Output(value) => self.output.write(value),
+        Status::NeedsInput => match self.input.read() {
+          Some(value) => self.computer.push_input(value),
+          None => {
+            return Err(ComputerError::MissingInput {
+              address: self.computer.instruction_pointer,
+            })
+          }
+        },
+        Status::Halted => return Ok(()),
+      }
+    }
+  }
+
+  pub fn computer(&self) -> &Computer {
+    &self.computer
+  }
+
+  pub fn output(&self) -> &O {
+    &self.output
+  }
+
+  pub fn into_parts(self) -> (Computer, I, O) {
+    (self.computer, self.input, self.output)
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use std::sync::mpsc;
+  use std::thread;
+
+  use super::*;
+  use crate::computer::parse_op_stack;
+  use crate::computer::pipeline::Pipeline;
+
+  // Adds up pairs of inputs, outputting each sum, until it reads a zero.
+  const SUMS: &str = "3,20,1005,20,7,99,0,3,21,1,20,21,22,4,22,1105,1,0";
+
+  fn sums() -> Computer {
+    Computer::new(parse_op_stack(SUMS).unwrap())
+  }
+
+  #[test]
+  fn test_queues_and_closures() {
+    let mut machine = Machine::new(sums(), VecDeque::from(vec![1, 2, 3, 4, 0]), Vec::new());
+    assert_eq!(machine.run(), Ok(()));
+    assert_eq!(machine.output(), &vec![3, 7]);
+
+    let mut inputs = vec![0, 5, 5].into_iter().rev();
+    let mut seen = Vec::new();
+    let mut machine = Machine::new(
+      sums(),
+      || inputs.next(),
+      |value: ComputerWord| seen.push(value),
+    );
+    machine.run().unwrap();
+    drop(machine);
+    assert_eq!(seen, vec![10]);
+
+    let mut dry = Machine::new(sums(), VecDeque::from(vec![1]), Vec::new());
+    assert_eq!(dry.run(), Err(ComputerError::MissingInput { address: 7 }));
+  }
+
+  #[test]
+  fn test_threads() {
+    // Day 7's feedback loop example, with each amplifier on its own thread.
+    let program = parse_op_stack(
+      "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+    )
+    .unwrap();
+    let phases = [9, 8, 7, 6, 5];
+
+    let (senders, receivers): (Vec<_>, Vec<_>) = phases.iter().map(|_| mpsc::channel()).unzip();
+    let (last, signals) = mpsc::channel();
+    senders[0].send(0).unwrap();
+    let handles: Vec<_> = receivers
+      .into_iter()
+      .enumerate()
+      .map(|(index, receiver)| {
+        let next = senders[(index + 1) % phases.len()].clone();
+        let last = last.clone();
+        let computer = Computer::new(program.clone()).add_input(phases[index]);
+        let is_last = index + 1 == phases.len();
+        thread::spawn(move || {
+          let output = move |value: ComputerWord| {
+            if is_last {
+              last.send(value).unwrap();
+            }
+            let _ = next.send(value);
+          };
+          Machine::new(computer, receiver, output).run()
+        })
+      })
+      .collect();
+    drop(senders);
+    drop(last);
+
+    for handle in handles {
+      assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+    let expected = Pipeline::new(&program, &phases).with_feedback().run(0);
+    assert_eq!(signals.iter().last(), expected.unwrap());
+  }
+}
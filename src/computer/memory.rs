@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Error, Formatter};
+
+use num::{Signed, ToPrimitive, Zero};
+
+use super::{ComputerError, ComputerWord};
+
+pub(super) const PAGE_SIZE: usize = 1024;
+
+/// Intcode memory: unbounded, zero-initialised and allocated a page at a time
+/// as addresses get written.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct ComputerState {
+  pages: BTreeMap<usize, Vec<ComputerWord>>,
+  len: usize,
+}
+
+impl ComputerState {
+  pub fn new() -> Self {
+    ComputerState::default()
+  }
+
+  /// Turns a word into an address, rejecting anything negative or past the
+  /// end of memory.
+  pub fn address(word: &ComputerWord) -> Result<usize, ComputerError> {
+    match word.to_usize() {
+      Some(address) if address < usize::MAX => Ok(address),
+      _ if word.is_negative() => Err(ComputerError::NegativeAddress(word.clone())),
+      _ => Err(ComputerError::AddressOutOfRange(word.clone())),
+    }
+  }
+
+  /// One past the highest address that has been loaded or written.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn get(&self, address: usize) -> ComputerWord {
+    self
+      .pages
+      .get(&(address / PAGE_SIZE))
+      .map(|page| page[address % PAGE_SIZE].clone())
+      .unwrap_or_else(ComputerWord::zero)
+  }
+
+  /// Panics when `address` is `usize::MAX`, as its word would lie past
+  /// [`ComputerState::len`]; [`ComputerState::address`] never returns it.
+  pub fn set(&mut self, address: usize, value: ComputerWord) {
+    let len = address
+      .checked_add(1)
+      .expect("Memory ends before usize::MAX");
+    let page = self
+      .pages
+      .entry(address / PAGE_SIZE)
+      .or_insert_with(|| vec![ComputerWord::zero(); PAGE_SIZE]);
+    page[address % PAGE_SIZE] = value;
+    self.len = self.len.max(len);
+  }
+
+  /// Extends [`ComputerState::len`] to at least `len` without allocating.
+  pub fn grow_to(&mut self, len: usize) {
+    self.len = self.len.max(len);
+  }
+
+  /// The allocated pages as `(first address, words)`, lowest address first.
+  pub fn pages(&self) -> impl Iterator<Item = (usize, &[ComputerWord])> {
+    self
+      .pages
+      .iter()
+      .map(|(index, page)| (index * PAGE_SIZE, page.as_slice()))
+  }
+
+  /// The stretches of each allocated page between its first and last
+  /// non-zero words, as `(first address, words)`, lowest address first.
+  pub fn runs(&self) -> impl Iterator<Item = (usize, &[ComputerWord])> {
+    self.pages().filter_map(|(start, page)| {
+      let first = page.iter().position(|word| !word.is_zero())?;
+      let last = page.iter().rposition(|word| !word.is_zero())?;
+      Some((start + first, &page[first..=last]))
+    })
+  }
+
+  /// Every word from address zero up to [`ComputerState::len`].
+  pub fn to_vec(&self) -> Vec<ComputerWord> {
+    (0..self.len).map(|address| self.get(address)).collect()
+  }
+}
+
+impl From<Vec<ComputerWord>> for ComputerState {
+  fn from(words: Vec<ComputerWord>) -> Self {
+    let mut state = ComputerState::new();
+    for (address, word) in words.into_iter().enumerate() {
+      state.set(address, word);
+    }
+    state
+  }
+}
+
+impl Debug for ComputerState {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    write!(
+      f,
+      "ComputerState {{ len: {}, pages: {} }}",
+      self.len,
+      self.pages.len()
+    )
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_untouched_memory_is_zero() {
+    let state = ComputerState::from(vec![1, 2, 3]);
+    assert_eq!(state.get(1), 2);
+    assert_eq!(state.get(3), 0);
+    assert_eq!(state.get(1_000_000), 0);
+    assert_eq!(state.len(), 3);
+  }
+
+  #[test]
+  fn test_sparse_writes() {
+    let mut state = ComputerState::new();
+    state.set(1 << 40, 7);
+    assert_eq!(state.get(1 << 40), 7);
+    assert_eq!(state.get((1 << 40) - 1), 0);
+    assert_eq!(state.len(), (1 << 40) + 1);
+    assert_eq!(state.pages.len(), 1);
+
+    state.set(usize::MAX - 1, 8);
+    assert_eq!(state.get(usize::MAX - 1), 8);
+    assert_eq!(state.len(), usize::MAX);
+  }
+
+  #[test]
+  #[should_panic(expected = "Memory ends before usize::MAX")]
+  fn test_write_past_the_end() {
+    ComputerState::new().set(usize::MAX, 8);
+  }
+
+  #[test]
+  fn test_negative_address() {
+    assert_eq!(ComputerState::address(&12), Ok(12));
+    assert_eq!(
+      ComputerState::address(&-1),
+      Err(ComputerError::NegativeAddress(-1))
+    );
+  }
+}
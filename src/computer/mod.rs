@@ -1,65 +1,182 @@
+// Words are `Copy` unless the `bigint` feature is on, so clone them regardless.
+#![allow(clippy::clone_on_copy)]
 use std::collections::VecDeque;
-use std::fmt::{Debug, Display, Error, Formatter};
 use std::str::FromStr;
 
-use num::{pow::Pow, One, ToPrimitive, Zero};
-
+use num::{CheckedAdd, Zero};
+
+pub mod ascii;
+pub mod assembler;
+pub mod binary;
+mod cache;
+pub mod cfg;
+pub mod compile;
+pub mod debugger;
+pub mod disassembler;
+mod error;
+pub mod loader;
+pub mod machine;
+mod memory;
+pub mod network;
 mod ops;
-
-pub use ops::{BinaryOp, JumpOp, OpArg, OpCode};
+pub mod pipeline;
+pub mod profile;
+pub mod search;
+mod snapshot;
+pub mod symbolic;
+pub mod trace;
+
+use cache::DecodeCache;
+pub use error::ComputerError;
+pub use memory::ComputerState;
+pub use ops::{BinaryOp, Instruction, JumpOp, OpArg, OpCode};
+use profile::Profile;
+use trace::{PendingEntry, Trace};
 
 trait InstructionSize {
   fn size(&self) -> usize;
 }
 
-pub fn parse_op_stack(input: &str) -> Vec<ComputerWord> {
+pub fn parse_op_stack(input: &str) -> Result<Vec<ComputerWord>, ComputerError> {
   input
     .split(',')
     .map(|s| s.trim())
-    .map(|s| (s, ComputerWord::from_str(s)))
-    .map(|(orig, result)| result.expect(&format!("Can't Parse '{}' to BigInt", orig)))
+    .enumerate()
+    .map(|(position, token)| {
+      ComputerWord::from_str(token).map_err(|_| ComputerError::Parse {
+        token: token.to_string(),
+        position,
+      })
+    })
     .collect()
 }
 
-pub fn from(input: &str) -> Computer {
-  Computer::new(parse_op_stack(input))
+pub fn from(input: &str) -> Result<Computer, ComputerError> {
+  Ok(Computer::new(parse_op_stack(input)?))
 }
 
-#[cfg(test)]
-pub fn run_single_input(program: &str, input: i64) -> ComputerWord {
-  from(program).add_input(input).run().pop().unwrap()
+/// Widens `i64` values into words, whichever type words are in this build.
+// Only a conversion with the `bigint` feature on.
+#[allow(clippy::useless_conversion)]
+pub fn words(values: impl IntoIterator<Item = i64>) -> Vec<ComputerWord> {
+  values.into_iter().map(ComputerWord::from).collect()
 }
 
+/// Echoes inputs until it reads a zero.
+#[cfg(all(test, not(feature = "bigint")))]
+const ECHO: &str = "3,9,4,9,1005,9,0,99,0,0";
+
+/// A single word of Intcode memory.
+///
+/// `i64` by default; build with the `bigint` feature for programs whose
+/// arithmetic doesn't fit in 64 bits.
+#[cfg(not(feature = "bigint"))]
 pub type ComputerWord = i64;
-pub type ComputerState = Vec<ComputerWord>;
+#[cfg(feature = "bigint")]
+pub type ComputerWord = num::BigInt;
+
+/// Why a call to [`Computer::resume`] handed control back to the caller.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Status {
+  /// The next instruction reads input but the input queue is empty.
+  /// Push more input and resume; the read is retried.
+  NeedsInput,
+  /// The program wrote a word of output.
+  Output(ComputerWord),
+  /// The program hit `99` (or ran off the end of memory).
+  Halted,
+}
 
-#[derive(Debug)]
+/// Cloning a computer forks it: the copy carries on from exactly the same
+/// point, independently of the original. See [`Computer::snapshot`] for
+/// saving one to disk.
+#[derive(Debug, Clone)]
 pub struct Computer {
   input: VecDeque<ComputerWord>,
   output: Vec<ComputerWord>,
   state: ComputerState,
   instruction_pointer: usize,
-  relative_base: usize,
+  relative_base: ComputerWord,
+  trace: Option<Trace>,
+  profile: Option<Profile>,
+  cache: DecodeCache,
+  /// Set once the halt has executed, so stepping a halted computer again
+  /// doesn't trace or count it twice.
+  halted: bool,
 }
 
-impl Computer {
-  pub fn new(state_vec: Vec<ComputerWord>) -> Self {
-    let mut state = vec![0; 2048];
-    state[..state_vec.len()].copy_from_slice(&state_vec);
+impl From<ComputerState> for Computer {
+  /// Boots a computer with `state` as its memory.
+  fn from(state: ComputerState) -> Self {
     Computer {
       state,
       input: VecDeque::new(),
       output: Vec::new(),
       instruction_pointer: 0,
-      relative_base: 0,
+      relative_base: ComputerWord::zero(),
+      trace: None,
+      profile: None,
+      cache: DecodeCache::default(),
+      halted: false,
     }
   }
+}
+
+impl Computer {
+  pub fn new(program: Vec<ComputerWord>) -> Self {
+    Computer::from(ComputerState::from(program))
+  }
+
+  /// Turns on tracing; every instruction executed from here on goes through `trace`.
+  pub fn with_trace(mut self, trace: Trace) -> Self {
+    self.trace = Some(trace);
+    self
+  }
+
+  pub fn trace(&self) -> Option<&Trace> {
+    self.trace.as_ref()
+  }
+
+  pub fn trace_mut(&mut self) -> Option<&mut Trace> {
+    self.trace.as_mut()
+  }
+
+  /// Turns tracing off, handing back what was recorded.
+  pub fn take_trace(&mut self) -> Option<Trace> {
+    self.trace.take()
+  }
+
+  /// Decodes every instruction afresh each time it runs, instead of caching
+  /// them. Only useful for measuring what the cache saves.
+  pub fn without_decode_cache(mut self) -> Self {
+    self.cache = DecodeCache::disabled();
+    self
+  }
+
+  /// Turns on profiling; every instruction executed from here on is counted in `profile`.
+  pub fn with_profile(mut self, profile: Profile) -> Self {
+    self.profile = Some(profile);
+    self
+  }
+
+  pub fn profile(&self) -> Option<&Profile> {
+    self.profile.as_ref()
+  }
+
+  /// Turns profiling off, handing back the counts.
+  pub fn take_profile(&mut self) -> Option<Profile> {
+    self.profile.take()
+  }
 
   pub fn add_input(mut self, input: i64) -> Self {
-    self.input.push_back(ComputerWord::from(input));
+    self.push_input(ComputerWord::from(input));
     self
   }
 
+  pub fn push_input(&mut self, input: ComputerWord) {
+    self.input.push_back(input);
+  }
+
   pub fn output(&mut self, value: &ComputerWord) {
     self.output.push(value.clone())
   }
@@ -68,105 +185,326 @@ impl Computer {
     self.state.len()
   }
 
-  pub fn resolve(&self, reference: usize) -> ComputerWord {
-    assert!(reference < self.state.capacity());
-    self
-      .state
-      .get(reference)
-      .cloned()
-      .unwrap_or(ComputerWord::zero())
+  pub fn is_empty(&self) -> bool {
+    self.state.is_empty()
   }
 
-  fn calc_relative(&self, relative_location: i32) -> usize {
-    let location = (self.relative_base as i32 + relative_location) as usize;
-    return location;
+  pub fn resolve(&self, reference: usize) -> ComputerWord {
+    self.state.get(reference)
   }
 
-  pub fn resolve_relative(&self, relative_location: i32) -> ComputerWord {
-    let location = self.calc_relative(relative_location);
-    assert!(location < self.state.len());
-    self.state[location].clone()
+  fn calc_relative(&self, relative_location: &ComputerWord) -> Result<usize, ComputerError> {
+    let location = CheckedAdd::checked_add(&self.relative_base, relative_location).ok_or(
+      ComputerError::Overflow {
+        address: self.instruction_pointer,
+      },
+    )?;
+    ComputerState::address(&location)
   }
 
-  pub fn set(&mut self, destination: OpArg, value: ComputerWord) {
-    match destination {
-      OpArg::Reference(dest) => self.state[dest] = value,
-      OpArg::Relative(dest) => {
-        let location = self.calc_relative(dest);
-        self.state[location] = value;
-      }
+  pub fn resolve_relative(
+    &self,
+    relative_location: &ComputerWord,
+  ) -> Result<ComputerWord, ComputerError> {
+    let location = self.calc_relative(relative_location)?;
+    Ok(self.state.get(location))
+  }
 
-      _ => unreachable!(),
+  /// The memory address an argument refers to, or `None` for a literal.
+  pub fn address_of(&self, arg: &OpArg) -> Result<Option<usize>, ComputerError> {
+    match arg {
+      OpArg::Reference(location) => ComputerState::address(location).map(Some),
+      OpArg::Relative(location) => self.calc_relative(location).map(Some),
+      OpArg::Literal(_) => Ok(None),
     }
   }
 
-  pub fn jump(&mut self, target: &OpArg) {
-    let target = target.resolve(self).to_usize().unwrap();
-    self.instruction_pointer = target;
+  pub fn set(&mut self, destination: &OpArg, value: ComputerWord) -> Result<(), ComputerError> {
+    let location = self
+      .address_of(destination)?
+      .ok_or(ComputerError::WriteToImmediate {
+        address: self.instruction_pointer,
+      })?;
+    self.write(location, value);
+    Ok(())
   }
 
-  const OP_CODE_SIZE: i32 = 100;
-  pub fn op_code(&self) -> u8 {
-    return (self.state[self.instruction_pointer].to_i32().unwrap() % Computer::OP_CODE_SIZE) as u8;
+  /// Every write to memory goes through here, so no stale instructions stay cached.
+  fn write(&mut self, address: usize, value: ComputerWord) {
+    self.state.set(address, value);
+    self.cache.invalidate(address);
   }
 
-  pub fn op_param_modes(&self) -> i32 {
-    let value = self.state[self.instruction_pointer];
-    value.to_i32().unwrap() / Computer::OP_CODE_SIZE
+  pub fn jump(&mut self, target: &OpArg) -> Result<(), ComputerError> {
+    let target = target.resolve(self)?;
+    self.instruction_pointer = ComputerState::address(&target)?;
+    Ok(())
   }
 
-  pub fn arg(&self, arg: usize) -> OpArg {
-    assert!(arg > 0);
-    let arg_value = self.state[self.instruction_pointer + arg].clone();
-    OpArg::factory(self, arg)(arg_value)
+  /// The instruction at the instruction pointer.
+  pub fn instruction(&self) -> Instruction<'_> {
+    Instruction::at(&self.state, self.instruction_pointer)
   }
 
-  pub fn binary_op(&self, op_code: impl Fn(BinaryOp) -> OpCode) -> OpCode {
-    let op1 = self.arg(1);
-    let op2 = self.arg(2);
-    let dest = self.arg(3);
+  pub fn op_code(&self) -> ComputerWord {
+    self.instruction().op_code()
+  }
 
-    let op_code = op_code(BinaryOp::new(op1, op2, dest));
-    //        println!("{:?}", op_code);
-    op_code
+  pub fn op_param_modes(&self) -> ComputerWord {
+    self.instruction().param_modes()
   }
 
-  pub fn next(&mut self) -> Option<OpCode> {
+  /// Decodes the instruction at the instruction pointer, or `None` past the end of memory.
+  ///
+  /// The instruction pointer only moves once the op is interpreted, so errors
+  /// raised while running it point at the instruction itself.
+  pub fn next(&self) -> Result<Option<OpCode>, ComputerError> {
     if self.instruction_pointer >= self.state.len() {
-      return None;
+      return Ok(None);
+    }
+
+    OpCode::read_op(self).map(Some)
+  }
+
+  /// Like [`Computer::next`], but decodes each instruction only once until
+  /// it's overwritten.
+  fn fetch(&mut self) -> Result<Option<OpCode>, ComputerError> {
+    if let Some(op_code) = self.cache.get(self.instruction_pointer) {
+      return Ok(Some(op_code.clone()));
+    }
+    let op_code = self.next()?;
+    if let Some(op_code) = &op_code {
+      self.cache.insert(self.instruction_pointer, op_code);
     }
+    Ok(op_code)
+  }
+
+  /// Executes a single instruction, returning a [`Status`] if it hands control back.
+  ///
+  /// Reading with an empty input queue and halting leave the instruction
+  /// pointer where it is, so stepping again retries the same instruction.
+  pub fn step(&mut self) -> Result<Option<Status>, ComputerError> {
+    let op_code = match self.fetch()? {
+      None => return Ok(Some(Status::Halted)),
+      Some(OpCode::Done) => {
+        self.halt()?;
+        return Ok(Some(Status::Halted));
+      }
+      Some(OpCode::ReadInput { .. }) if self.input.is_empty() => {
+        return Ok(Some(Status::NeedsInput))
+      }
+      Some(op_code) => op_code,
+    };
+
+    self.execute(&op_code)?;
 
-    let op_code = OpCode::read_op(self);
-    self.instruction_pointer += op_code.size();
-    Some(op_code)
+    if let OpCode::SaveOutput { .. } = op_code {
+      let value = self.output.pop().expect("Output was just written");
+      return Ok(Some(Status::Output(value)));
+    }
+    Ok(None)
   }
 
-  pub fn eval(&mut self) -> ComputerWord {
+  /// Runs until the program produces output, needs input it doesn't have, or halts.
+  ///
+  /// Unlike [`Computer::run`] this leaves the machine intact, so it can be fed
+  /// more input with [`Computer::push_input`] and resumed where it left off.
+  pub fn resume(&mut self) -> Result<Status, ComputerError> {
+    loop {
+      if let Some(status) = self.step()? {
+        return Ok(status);
+      }
+    }
+  }
+
+  pub fn eval(&mut self) -> Result<ComputerWord, ComputerError> {
     self.eval_at(0)
   }
 
-  pub fn eval_at(&mut self, result_location: usize) -> ComputerWord {
-    self.interpret();
-    return self.resolve(result_location);
+  pub fn eval_at(&mut self, result_location: usize) -> Result<ComputerWord, ComputerError> {
+    self.interpret()?;
+    Ok(self.resolve(result_location))
   }
 
-  pub fn run(mut self) -> Vec<ComputerWord> {
-    self.interpret();
-    self.output
+  pub fn run(mut self) -> Result<Vec<ComputerWord>, ComputerError> {
+    self.interpret()?;
+    Ok(self.output)
   }
 
-  fn interpret(&mut self) -> Vec<OpCode> {
-    let mut result = Vec::new();
+  fn execute(&mut self, op_code: &OpCode) -> Result<(), ComputerError> {
+    if let Some(profile) = &mut self.profile {
+      profile.record(self.instruction_pointer, op_code);
+    }
+    if self.trace.is_none() {
+      return op_code.interpret(self);
+    }
+
+    let pending = PendingEntry::before(self, op_code)?;
+    op_code.interpret(self)?;
+    pending.finish(self);
+    Ok(())
+  }
 
-    while let Some(op_code) = self.next() {
-      op_code.interpret(self);
-      let is_done = op_code.is_done();
-      result.push(op_code);
-      if is_done {
-        break;
+  fn interpret(&mut self) -> Result<(), ComputerError> {
+    while let Some(op_code) = self.fetch()? {
+      if op_code.is_done() {
+        return self.halt();
       }
+      self.execute(&op_code)?;
+    }
+    Ok(())
+  }
+
+  /// Executes the halt the first time it's reached; after that the computer
+  /// just stays halted.
+  fn halt(&mut self) -> Result<(), ComputerError> {
+    if !self.halted {
+      self.execute(&OpCode::Done)?;
+      self.halted = true;
     }
+    Ok(())
+  }
+}
+
+// Test programs and expectations are written with `i64` literals; the `bigint`
+// build has its own tests below.
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_resume_waits_for_input() {
+    let mut computer = from(ECHO).unwrap();
+
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+
+    computer.push_input(7);
+    assert_eq!(computer.resume(), Ok(Status::Output(7)));
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+
+    computer.push_input(0);
+    assert_eq!(computer.resume(), Ok(Status::Output(0)));
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+  }
+
+  #[test]
+  fn test_resume_keeps_relative_base() {
+    let mut computer = from("109,10,203,0,204,0,99").unwrap();
+
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+    computer.push_input(42);
+    assert_eq!(computer.resume(), Ok(Status::Output(42)));
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+  }
+
+  #[test]
+  fn test_memory_past_program() {
+    let run = |program: &str| from(program).and_then(|computer| computer.run());
+
+    assert_eq!(run("1101,1,2,5000,4,5000,99"), Ok(vec![3]));
+    assert_eq!(run("109,100000,21101,4,5,7,204,7,99"), Ok(vec![9]));
+  }
+
+  #[test]
+  fn test_long_program() {
+    let mut program = vec![1101, 2, 3, 100_000];
+    program.extend((0..5000).flat_map(|_| vec![1101, 0, 0, 100_001]));
+    program.extend(vec![4, 100_000, 99]);
+    assert!(program.len() > 2048);
+    assert_eq!(Computer::new(program).run(), Ok(vec![5]));
+  }
+
+  #[test]
+  fn test_errors() {
+    let run = |program: &str| from(program).and_then(|computer| computer.run());
+
+    assert_eq!(
+      run("1,0,0,0,42"),
+      Err(ComputerError::InvalidOpCode {
+        op_code: 42,
+        address: 4
+      })
+    );
+    assert_eq!(
+      run("301,0,0,0,99"),
+      Err(ComputerError::InvalidParameterMode {
+        mode: 3,
+        address: 0
+      })
+    );
+    assert_eq!(
+      run("11101,1,1,0,99"),
+      Err(ComputerError::WriteToImmediate { address: 0 })
+    );
+    assert_eq!(run("4,-1,99"), Err(ComputerError::NegativeAddress(-1)));
+    assert_eq!(
+      run("1101,9223372036854775807,1,0,99"),
+      Err(ComputerError::Overflow { address: 0 })
+    );
+    assert_eq!(
+      run("104,1,3,0,99"),
+      Err(ComputerError::MissingInput { address: 2 })
+    );
+    assert_eq!(
+      run("1,0,x,0,99"),
+      Err(ComputerError::Parse {
+        token: "x".to_string(),
+        position: 2
+      })
+    );
+  }
+}
+
+#[cfg(all(test, feature = "bigint"))]
+mod bigint_tests {
+  use super::*;
+
+  #[test]
+  fn test_past_64_bits() {
+    // Squares 2^40, then adds the result to itself.
+    let output = from("1102,1099511627776,1099511627776,11,1,11,11,11,4,11,99,0")
+      .unwrap()
+      .run()
+      .unwrap();
+    assert_eq!(output, vec![ComputerWord::from(2) << 80]);
+  }
+
+  #[test]
+  fn test_snapshot_round_trip() {
+    let mut computer = from("1102,1099511627776,1099511627776,9,3,10,99,0,0,0").unwrap();
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+
+    let snapshot = computer.snapshot();
+    assert!(snapshot.contains(&(ComputerWord::from(1) << 80).to_string()));
+    let mut restored = Computer::restore(&snapshot).unwrap();
+    restored.push_input(ComputerWord::from(7));
+    assert_eq!(restored.eval_at(9), Ok(ComputerWord::from(1) << 80));
+  }
 
-    return result;
+  #[test]
+  fn test_end_of_memory() {
+    let run = |program: &str| from(program).and_then(|computer| computer.run());
+
+    assert_eq!(
+      run("1101,1,2,18446744073709551615,99"),
+      Err(ComputerError::AddressOutOfRange(ComputerWord::from(
+        usize::MAX
+      )))
+    );
+    assert_eq!(run("1101,1,2,18446744073709551614,99"), Ok(vec![]));
+    assert_eq!(
+      run("4,1208925819614629174706176,99"),
+      Err(ComputerError::AddressOutOfRange(
+        ComputerWord::from(1) << 80
+      ))
+    );
+    assert_eq!(
+      run("4,-1208925819614629174706176,99"),
+      Err(ComputerError::NegativeAddress(
+        -(ComputerWord::from(1) << 80)
+      ))
+    );
   }
 }
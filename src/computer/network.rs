@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+
+use num::{One, ToPrimitive};
+
+use super::{Computer, ComputerError, ComputerWord, Status};
+
+/// What machines on a [`Network`] send each other, written as three words
+/// of output.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Packet {
+  pub destination: ComputerWord,
+  pub x: ComputerWord,
+  pub y: ComputerWord,
+}
+
+/// What a [`Monitor`] wants the network to do next.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Control {
+  Continue,
+  /// Deliver a packet, then carry on. Packets to addresses outside the
+  /// network are dropped.
+  Send(Packet),
+  Stop,
+}
+
+/// Watches a [`Network`] from the outside.
+pub trait Monitor {
+  /// A packet was sent to an address with no machine on it.
+  fn unroutable(&mut self, packet: Packet) -> Control;
+
+  /// Every machine is waiting on an empty queue and nothing is in flight,
+  /// so the network will do nothing more unless sent a packet.
+  fn idle(&mut self) -> Control;
+}
+
+/// The monitor from day 23: it keeps the last packet sent to address 255
+/// and, whenever the network goes idle, sends it on to address 0. It stops
+/// once it sends the same `y` twice in a row.
+#[derive(Debug, Clone, Default)]
+pub struct Nat {
+  first: Option<Packet>,
+  last: Option<Packet>,
+  delivered: Option<ComputerWord>,
+  repeated: Option<ComputerWord>,
+}
+
+impl Nat {
+  pub const ADDRESS: i64 = 255;
+
+  pub fn new() -> Self {
+    Nat::default()
+  }
+
+  /// The first packet the NAT was sent.
+  pub fn first(&self) -> Option<&Packet> {
+    self.first.as_ref()
+  }
+
+  /// The `y` it sent twice in a row, once it has.
+  pub fn repeated(&self) -> Option<&ComputerWord> {
+    self.repeated.as_ref()
+  }
+}
+
+impl Monitor for Nat {
+  fn unroutable(&mut self, packet: Packet) -> Control {
+    if packet.destination == ComputerWord::from(Nat::ADDRESS) {
+      self.first.get_or_insert_with(|| packet.clone());
+      self.last = Some(packet);
+    }
+    Control::Continue
+  }
+
+  fn idle(&mut self) -> Control {
+    let packet = match &self.last {
+      Some(packet) => packet,
+      // Nothing to wake the network with.
+      None => return Control::Stop,
+    };
+    if self.delivered.as_ref() == Some(&packet.y) {
+      self.repeated = Some(packet.y.clone());
+      return Control::Stop;
+    }
+    self.delivered = Some(packet.y.clone());
+    Control::Send(Packet {
+      destination: ComputerWord::from(0),
+      ..packet.clone()
+    })
+  }
+}
+
+/// How a machine's turn went.
+enum Turn {
+  Halted,
+  /// It read from an empty queue and sent nothing.
+  Quiet,
+  Busy,
+  Stop,
+}
+
+/// Machines running copies of one program, passing packets between them.
+///
+/// Machines are booted with their address as their first input and take
+/// turns in address order, so a run always goes the same way. Each turn a
+/// machine runs until it next wants input, and is then given the packet at
+/// the head of its queue, or -1 if the queue is empty.
+pub struct Network {
+  computers: Vec<Computer>,
+  halted: Vec<bool>,
+  queues: Vec<VecDeque<Packet>>,
+  /// Output words that don't yet make up a whole packet.
+  partial: Vec<Vec<ComputerWord>>,
+}
+
+impl Network {
+  pub fn new(program: &[ComputerWord], size: usize) -> Self {
+    Network {
+      computers: (0..size)
+        .map(|address| Computer::new(program.to_vec()).add_input(address as i64))
+        .collect(),
+      halted: vec![false; size],
+      queues: vec![VecDeque::new(); size],
+      partial: vec![Vec::new(); size],
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.computers.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.computers.is_empty()
+  }
+
+  /// Queues `packet` for its destination, returning it if there's no
+  /// machine there.
+  pub fn send(&mut self, packet: Packet) -> Option<Packet> {
+    match packet
+      .destination
+      .to_usize()
+      .filter(|&address| address < self.len())
+    {
+      Some(address) => {
+        self.queues[address].push_back(packet);
+        None
+      }
+      None => Some(packet),
+    }
+  }
+
+  /// Runs until `monitor` says to stop or every machine has halted.
+  ///
+  /// The network counts as idle after two rounds in a row in which every
+  /// machine read from an empty queue and nothing was sent: the first
+  /// round may still be finishing off input read before it.
+  pub fn run(&mut self, monitor: &mut impl Monitor) -> Result<(), ComputerError> {
+    let mut quiet_rounds = 0;
+    loop {
+      let mut quiet = true;
+      let mut running = false;
+      for address in 0..self.len() {
+        match self.turn(address, monitor)? {
+          Turn::Halted => continue,
+          Turn::Quiet => {}
+          Turn::Busy => quiet = false,
+          Turn::Stop => return Ok(()),
+        }
+        running = true;
+      }
+      if !running {
+        return Ok(());
+      }
+
+      quiet_rounds = if quiet { quiet_rounds + 1 } else { 0 };
+      if quiet_rounds >= 2 {
+        quiet_rounds = 0;
+        match monitor.idle() {
+          Control::Continue => {}
+          Control::Send(packet) => {
+            self.send(packet);
+          }
+          Control::Stop => return Ok(()),
+        }
+      }
+    }
+  }
+
+  fn turn(&mut self, address: usize, monitor: &mut impl Monitor) -> Result<Turn, ComputerError> {
+    if self.halted[address] {
+      return Ok(Turn::Halted);
+    }
+
+    let mut busy = false;
+    loop {
+      match self.computers[address].resume()? {
+        Status::Output(value) => {
+          busy = true;
+          let partial = &mut self.partial[address];
+          partial.push(value);
+          let packet = match partial.as_slice() {
+            [destination, x, y] => Packet {
+              destination: destination.clone(),
+              x: x.clone(),
+              y: y.clone(),
+            },
+            _ => continue,
+          };
+          partial.clear();
+          let unroutable = match self.send(packet) {
+            Some(packet) => packet,
+            None => continue,
+          };
+          match monitor.unroutable(unroutable) {
+            Control::Continue => {}
+            Control::Send(packet) => {
+              self.send(packet);
+            }
+            Control::Stop => return Ok(Turn::Stop),
+          }
+        }
+        Status::NeedsInput => break,
+        Status::Halted => {
+          self.halted[address] = true;
+          return Ok(if busy { Turn::Busy } else { Turn::Halted });
+        }
+      }
+    }
+
+    let computer = &mut self.computers[address];
+    match self.queues[address].pop_front() {
+      Some(packet) => {
+        computer.push_input(packet.x);
+        computer.push_input(packet.y);
+        Ok(Turn::Busy)
+      }
+      None => {
+        computer.push_input(-ComputerWord::one());
+        Ok(if busy { Turn::Busy } else { Turn::Quiet })
+      }
+    }
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::assembler::assemble;
+
+  // Machine 0 starts things off by sending (0, 7) to machine 1. Each
+  // machine then passes on whatever it's sent to the next address, adding
+  // one to x, except machine 2, which sends it to the NAT.
+  const RELAY: &str = "
+      in -> @addr
+      jnz @addr, loop
+      out 1
+      out 0
+      out 7
+    loop:
+      in -> @x
+      eq @x, -1 -> @t
+      jnz @t, loop
+      in -> @y
+      add @addr, 1 -> @dest
+      eq @dest, 3 -> @t
+      jz @t, send
+      add 255, 0 -> @dest
+    send:
+      out @dest
+      add @x, 1 -> @x
+      out @x
+      out @y
+      jz 0, loop
+    addr: data 0
+    x: data 0
+    y: data 0
+    t: data 0
+    dest: data 0
+  ";
+
+  fn packet(destination: i64, x: i64, y: i64) -> Packet {
+    Packet { destination, x, y }
+  }
+
+  #[test]
+  fn test_nat() {
+    let mut network = Network::new(&assemble(RELAY).unwrap(), 3);
+    let mut nat = Nat::new();
+    network.run(&mut nat).unwrap();
+    assert_eq!(nat.first(), Some(&packet(255, 2, 7)));
+    // The NAT wakes machine 0 with (2, 7), which comes back as (5, 7).
+    assert_eq!(nat.last, Some(packet(255, 5, 7)));
+    assert_eq!(nat.repeated(), Some(&7));
+  }
+
+  /// Records everything it sees and stops after a few idle spells.
+  #[derive(Default)]
+  struct Log {
+    events: Vec<String>,
+  }
+
+  impl Monitor for Log {
+    fn unroutable(&mut self, packet: Packet) -> Control {
+      self.events.push(format!("{:?}", packet));
+      Control::Continue
+    }
+
+    fn idle(&mut self) -> Control {
+      self.events.push("idle".to_string());
+      match self.events.iter().filter(|event| *event == "idle").count() {
+        1 => Control::Send(packet(1, 10, 20)),
+        2 => Control::Send(packet(9, 0, 0)),
+        _ => Control::Stop,
+      }
+    }
+  }
+
+  #[test]
+  fn test_monitor_and_determinism() {
+    let program = assemble(RELAY).unwrap();
+    let run = || {
+      let mut network = Network::new(&program, 3);
+      let mut log = Log::default();
+      network.run(&mut log).unwrap();
+      log.events
+    };
+    let events = run();
+    assert_eq!(
+      events,
+      vec![
+        format!("{:?}", packet(255, 2, 7)),
+        "idle".to_string(),
+        format!("{:?}", packet(255, 12, 20)),
+        "idle".to_string(),
+        "idle".to_string(),
+      ]
+    );
+    assert_eq!(run(), events);
+
+    let mut network = Network::new(&program, 3);
+    assert_eq!(network.send(packet(3, 0, 0)), Some(packet(3, 0, 0)));
+    assert_eq!(network.send(packet(2, 0, 0)), None);
+  }
+
+  #[test]
+  fn test_halting() {
+    // Every machine outputs a packet for the next address and halts.
+    let program =
+      assemble("in -> @n\nadd @n, 1 -> @n\nout @n\nout 1\nout 2\nhlt\nn: data 0").unwrap();
+    let mut network = Network::new(&program, 2);
+    let mut log = Log::default();
+    assert_eq!(network.run(&mut log), Ok(()));
+    assert_eq!(log.events, vec![format!("{:?}", packet(2, 1, 2))]);
+    assert_eq!(network.queues[1], vec![packet(1, 1, 2)]);
+  }
+}
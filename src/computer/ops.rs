@@ -1,5 +1,5 @@
-pub use super::{Computer, ComputerWord};
-use num::traits::{One, ToPrimitive, Zero};
+pub use super::{Computer, ComputerError, ComputerState, ComputerWord};
+use num::traits::{CheckedAdd, CheckedMul, One, ToPrimitive, Zero};
 use std::fmt::{Debug, Display, Error, Formatter};
 
 use super::InstructionSize;
@@ -7,8 +7,8 @@ use super::InstructionSize;
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub enum OpArg {
   Literal(ComputerWord),
-  Reference(usize),
-  Relative(i32),
+  Reference(ComputerWord),
+  Relative(ComputerWord),
 }
 
 impl Debug for OpArg {
@@ -28,36 +28,138 @@ impl Display for OpArg {
 }
 
 impl OpArg {
-  pub fn resolve<'a>(&'a self, computer: &'a Computer) -> ComputerWord {
+  /// The parameter mode digit this argument is encoded with.
+  pub fn mode(&self) -> ComputerWord {
+    let mode = match self {
+      OpArg::Reference(_) => 0,
+      OpArg::Literal(_) => 1,
+      OpArg::Relative(_) => 2,
+    };
+    ComputerWord::from(mode)
+  }
+
+  /// The raw word stored for this argument.
+  pub fn value(&self) -> ComputerWord {
+    match self {
+      OpArg::Literal(value) | OpArg::Reference(value) | OpArg::Relative(value) => value.clone(),
+    }
+  }
+
+  pub fn resolve(&self, computer: &Computer) -> Result<ComputerWord, ComputerError> {
     match self {
-      OpArg::Literal(lit) => *lit,
-      OpArg::Reference(loc) => computer.resolve(*loc),
-      OpArg::Relative(rel_loc) => computer.resolve_relative(*rel_loc),
+      OpArg::Literal(lit) => Ok(lit.clone()),
+      OpArg::Reference(loc) => Ok(computer.resolve(ComputerState::address(loc)?)),
+      OpArg::Relative(rel_loc) => computer.resolve_relative(rel_loc),
     }
   }
 
-  pub fn factory(computer: &Computer, arg: usize) -> impl Fn(ComputerWord) -> OpArg {
+  pub fn factory(
+    instruction: &Instruction,
+    arg: usize,
+  ) -> Result<impl Fn(ComputerWord) -> OpArg, ComputerError> {
     assert!(arg > 0);
 
-    let param_modes = computer.op_param_modes();
-
-    let arg_denominator = 10i32.pow(arg.to_u32().unwrap() - 1);
-    let arg_mode = (param_modes / arg_denominator) % 10;
-
-    //        println!("Arg({}) mode: {}", arg, arg_mode);
-    move |value| match arg_mode {
-      0 => OpArg::Reference(value.to_usize().expect(&format!(
-        "Trying to convert to i32: {} argmode={}",
-        value, arg_mode
-      ))),
-      1 => OpArg::Literal(value),
-      2 => OpArg::Relative(value.to_i32().expect(&format!(
-        "Trying to convert to i32: {} argmode={}",
-        value, arg_mode
-      ))),
-      _ => unimplemented!(),
+    let param_modes = instruction.param_modes();
+
+    let arg_denominator = num::pow(ComputerWord::from(10), arg - 1);
+    let arg_mode = (param_modes / arg_denominator) % ComputerWord::from(10);
+
+    let make_arg = match arg_mode.to_u8() {
+      Some(0) => OpArg::Reference,
+      Some(1) => OpArg::Literal,
+      Some(2) => OpArg::Relative,
+      _ => {
+        return Err(ComputerError::InvalidParameterMode {
+          mode: arg_mode,
+          address: instruction.address(),
+        })
+      }
+    };
+    Ok(make_arg)
+  }
+}
+
+/// A view of the words making up one instruction, wherever it sits in memory.
+pub struct Instruction<'a> {
+  state: &'a ComputerState,
+  address: usize,
+}
+
+impl<'a> Instruction<'a> {
+  pub fn at(state: &'a ComputerState, address: usize) -> Self {
+    Instruction { state, address }
+  }
+
+  pub fn address(&self) -> usize {
+    self.address
+  }
+
+  fn op_code_size() -> ComputerWord {
+    ComputerWord::from(100)
+  }
+
+  pub fn op_code(&self) -> ComputerWord {
+    self.state.get(self.address) % Instruction::op_code_size()
+  }
+
+  pub fn param_modes(&self) -> ComputerWord {
+    self.state.get(self.address) / Instruction::op_code_size()
+  }
+
+  pub fn arg(&self, arg: usize) -> Result<OpArg, ComputerError> {
+    assert!(arg > 0);
+    let arg_value = self.state.get(self.address + arg);
+    Ok(OpArg::factory(self, arg)?(arg_value))
+  }
+
+  /// Like [`Instruction::arg`], but for parameters the instruction writes to.
+  pub fn destination_arg(&self, arg: usize) -> Result<OpArg, ComputerError> {
+    match self.arg(arg)? {
+      OpArg::Literal(_) => Err(ComputerError::WriteToImmediate {
+        address: self.address,
+      }),
+      destination => Ok(destination),
     }
   }
+
+  pub fn binary_op(&self, op_code: impl Fn(BinaryOp) -> OpCode) -> Result<OpCode, ComputerError> {
+    let op1 = self.arg(1)?;
+    let op2 = self.arg(2)?;
+    let dest = self.destination_arg(3)?;
+
+    Ok(op_code(BinaryOp::new(op1, op2, dest)))
+  }
+
+  /// Decodes the words into an [`OpCode`].
+  pub fn decode(&self) -> Result<OpCode, ComputerError> {
+    let op_code = match self.op_code().to_u8() {
+      Some(1) => self.binary_op(OpCode::Add)?,
+      Some(2) => self.binary_op(OpCode::Mul)?,
+      Some(3) => OpCode::ReadInput {
+        to: self.destination_arg(1)?,
+      },
+      Some(4) => OpCode::SaveOutput { from: self.arg(1)? },
+      Some(5) => OpCode::JumpIfNonZero(JumpOp {
+        test: self.arg(1)?,
+        target: self.arg(2)?,
+      }),
+      Some(6) => OpCode::JumpIfZero(JumpOp {
+        test: self.arg(1)?,
+        target: self.arg(2)?,
+      }),
+      Some(7) => self.binary_op(OpCode::LessThan)?,
+      Some(8) => self.binary_op(OpCode::Equals)?,
+      Some(9) => OpCode::RelativeAdjustment(self.arg(1)?),
+      Some(99) => OpCode::Done,
+      _ => {
+        return Err(ComputerError::InvalidOpCode {
+          op_code: self.state.get(self.address),
+          address: self.address,
+        })
+      }
+    };
+    Ok(op_code)
+  }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -83,6 +185,12 @@ pub struct JumpOp {
   target: OpArg,
 }
 
+impl JumpOp {
+  pub fn new(test: OpArg, target: OpArg) -> Self {
+    JumpOp { test, target }
+  }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum OpCode {
   Add(BinaryOp),
@@ -110,76 +218,137 @@ impl InstructionSize for OpCode {
   }
 }
 
+impl Display for OpCode {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    let name = self.mnemonic();
+    match self {
+      OpCode::Add(op) | OpCode::Mul(op) | OpCode::LessThan(op) | OpCode::Equals(op) => {
+        write!(f, "{} {}, {} -> {}", name, op.op1, op.op2, op.destination)
+      }
+      OpCode::JumpIfNonZero(op) | OpCode::JumpIfZero(op) => {
+        write!(f, "{} {}, {}", name, op.test, op.target)
+      }
+      OpCode::ReadInput { to } => write!(f, "{} -> {}", name, to),
+      OpCode::SaveOutput { from: arg } | OpCode::RelativeAdjustment(arg) => {
+        write!(f, "{} {}", name, arg)
+      }
+      OpCode::Done => write!(f, "{}", name),
+    }
+  }
+}
+
 impl OpCode {
-  pub fn is_done(&self) -> bool {
+  pub fn mnemonic(&self) -> &'static str {
     match self {
-      OpCode::Done => true,
-      _ => false,
+      OpCode::Add(_) => "add",
+      OpCode::Mul(_) => "mul",
+      OpCode::ReadInput { .. } => "in",
+      OpCode::SaveOutput { .. } => "out",
+      OpCode::JumpIfNonZero(_) => "jnz",
+      OpCode::JumpIfZero(_) => "jz",
+      OpCode::LessThan(_) => "lt",
+      OpCode::Equals(_) => "eq",
+      OpCode::RelativeAdjustment(_) => "arb",
+      OpCode::Done => "hlt",
     }
   }
 
-  pub fn read_op(computer: &Computer) -> OpCode {
-    match computer.op_code() {
-      1 => computer.binary_op(OpCode::Add),
-      2 => computer.binary_op(OpCode::Mul),
-      3 => OpCode::ReadInput {
-        to: computer.arg(1),
-      },
-      4 => OpCode::SaveOutput {
-        from: computer.arg(1),
-      },
-      5 => OpCode::JumpIfNonZero(JumpOp {
-        test: computer.arg(1),
-        target: computer.arg(2),
-      }),
-      6 => OpCode::JumpIfZero(JumpOp {
-        test: computer.arg(1),
-        target: computer.arg(2),
-      }),
-      7 => computer.binary_op(OpCode::LessThan),
-      8 => computer.binary_op(OpCode::Equals),
-      9 => OpCode::RelativeAdjustment(computer.arg(1)),
-      99 => return OpCode::Done,
-      _ => panic!(
-        "Invalid opcode: {} @{}",
-        computer.op_code(),
-        computer.instruction_pointer
-      ),
+  /// The two-digit opcode, without parameter modes.
+  pub fn op_number(&self) -> ComputerWord {
+    let op_number = match self {
+      OpCode::Add(_) => 1,
+      OpCode::Mul(_) => 2,
+      OpCode::ReadInput { .. } => 3,
+      OpCode::SaveOutput { .. } => 4,
+      OpCode::JumpIfNonZero(_) => 5,
+      OpCode::JumpIfZero(_) => 6,
+      OpCode::LessThan(_) => 7,
+      OpCode::Equals(_) => 8,
+      OpCode::RelativeAdjustment(_) => 9,
+      OpCode::Done => 99,
+    };
+    ComputerWord::from(op_number)
+  }
+
+  /// Arguments in the order they are encoded.
+  pub fn args(&self) -> Vec<&OpArg> {
+    match self {
+      OpCode::Add(op) | OpCode::Mul(op) | OpCode::LessThan(op) | OpCode::Equals(op) => {
+        vec![&op.op1, &op.op2, &op.destination]
+      }
+      OpCode::JumpIfNonZero(op) | OpCode::JumpIfZero(op) => vec![&op.test, &op.target],
+      OpCode::ReadInput { to: arg }
+      | OpCode::SaveOutput { from: arg }
+      | OpCode::RelativeAdjustment(arg) => vec![arg],
+      OpCode::Done => vec![],
     }
   }
 
+  /// The arguments the instruction reads from, in order.
+  pub fn sources(&self) -> Vec<&OpArg> {
+    match self {
+      OpCode::Add(op) | OpCode::Mul(op) | OpCode::LessThan(op) | OpCode::Equals(op) => {
+        vec![&op.op1, &op.op2]
+      }
+      OpCode::ReadInput { .. } => vec![],
+      _ => self.args(),
+    }
+  }
+
+  /// The argument the instruction writes to, if any.
+  pub fn destination(&self) -> Option<&OpArg> {
+    match self {
+      OpCode::Add(op) | OpCode::Mul(op) | OpCode::LessThan(op) | OpCode::Equals(op) => {
+        Some(&op.destination)
+      }
+      OpCode::ReadInput { to } => Some(to),
+      _ => None,
+    }
+  }
+
+  /// The inverse of [`Instruction::decode`].
+  pub fn encode(&self) -> Vec<ComputerWord> {
+    let args = self.args();
+    let modes = args.iter().rev().fold(ComputerWord::zero(), |modes, arg| {
+      modes * ComputerWord::from(10) + arg.mode()
+    });
+
+    let mut words = vec![modes * Instruction::op_code_size() + self.op_number()];
+    words.extend(args.iter().map(|arg| arg.value()));
+    words
+  }
+
+  pub fn is_done(&self) -> bool {
+    matches!(self, OpCode::Done)
+  }
+
+  pub fn read_op(computer: &Computer) -> Result<OpCode, ComputerError> {
+    computer.instruction().decode()
+  }
+
   fn binary_op(
     computer: &mut Computer,
     binary_op: &BinaryOp,
-    _op: &str,
-    operation: impl Fn(ComputerWord, ComputerWord) -> ComputerWord,
-  ) {
-    //println!("Operation({op}): {:?}", binary_op, op = _op);
-    let x = binary_op.op1.resolve(computer);
-    let y = binary_op.op2.resolve(computer);
-    let result = operation(x, y);
-    //        println!(
-    //            "[{dest}] = {} ({} {op} {})",
-    //            result,
-    //            x,
-    //            y,
-    //            op = _op,
-    //            dest = binary_op.destination
-    //        );
-    computer.set(binary_op.destination.clone(), result);
+    operation: impl Fn(ComputerWord, ComputerWord) -> Option<ComputerWord>,
+  ) -> Result<(), ComputerError> {
+    let x = binary_op.op1.resolve(computer)?;
+    let y = binary_op.op2.resolve(computer)?;
+    let result = operation(x, y).ok_or(ComputerError::Overflow {
+      address: computer.instruction_pointer,
+    })?;
+    computer.set(&binary_op.destination, result)
   }
 
   fn bool_op(
     computer: &mut Computer,
     binary_op: &BinaryOp,
-    _op: &str,
     condition: impl Fn(ComputerWord, ComputerWord) -> bool,
-  ) {
-    OpCode::binary_op(computer, binary_op, _op, |a, b| {
+  ) -> Result<(), ComputerError> {
+    OpCode::binary_op(computer, binary_op, |a, b| {
       if condition(a, b) {
-        ComputerWord::one()
+        Some(ComputerWord::one())
       } else {
-        ComputerWord::zero()
+        Some(ComputerWord::zero())
       }
     })
   }
@@ -187,43 +356,60 @@ impl OpCode {
   fn jump(
     computer: &mut Computer,
     jump_op: &JumpOp,
-    _op: &str,
     condition: impl Fn(ComputerWord) -> bool,
-  ) {
-    //        println!("Operation({op}): {:?}", jump_op, op = _op);
-    if condition(jump_op.test.resolve(computer)) {
-      computer.jump(&jump_op.target)
+  ) -> Result<bool, ComputerError> {
+    if condition(jump_op.test.resolve(computer)?) {
+      computer.jump(&jump_op.target)?;
+      return Ok(true);
     }
+    Ok(false)
   }
 
-  pub fn interpret(&self, computer: &mut Computer) {
+  /// Executes the op against `computer` and moves its instruction pointer on.
+  pub fn interpret(&self, computer: &mut Computer) -> Result<(), ComputerError> {
     match self {
       OpCode::Add(bin_op) => {
-        OpCode::binary_op(computer, bin_op, "+", |x, y| x.checked_add(y).unwrap())
+        OpCode::binary_op(computer, bin_op, |x, y| CheckedAdd::checked_add(&x, &y))?
       }
       OpCode::Mul(bin_op) => {
-        OpCode::binary_op(computer, bin_op, "*", |x, y| x.checked_mul(y).unwrap())
+        OpCode::binary_op(computer, bin_op, |x, y| CheckedMul::checked_mul(&x, &y))?
       }
-      OpCode::LessThan(bin_op) => OpCode::bool_op(computer, bin_op, "<", |x, y| x < y),
-      OpCode::Equals(bin_op) => OpCode::bool_op(computer, bin_op, "==", |x, y| x == y),
+      OpCode::LessThan(bin_op) => OpCode::bool_op(computer, bin_op, |x, y| x < y)?,
+      OpCode::Equals(bin_op) => OpCode::bool_op(computer, bin_op, |x, y| x == y)?,
       OpCode::JumpIfNonZero(jump_op) => {
-        OpCode::jump(computer, jump_op, "jnz", |i| i != ComputerWord::zero())
+        if OpCode::jump(computer, jump_op, |i| !i.is_zero())? {
+          return Ok(());
+        }
       }
       OpCode::JumpIfZero(jump_op) => {
-        OpCode::jump(computer, jump_op, "jz", |i| i == ComputerWord::zero())
+        if OpCode::jump(computer, jump_op, |i| i.is_zero())? {
+          return Ok(());
+        }
       }
       OpCode::ReadInput { to } => {
-        let input = computer.input.pop_front().expect("No Input");
-        computer.set(to.clone(), input)
+        let input = computer
+          .input
+          .pop_front()
+          .ok_or(ComputerError::MissingInput {
+            address: computer.instruction_pointer,
+          })?;
+        computer.set(to, input)?
       }
       OpCode::SaveOutput { from } => {
-        let result = from.resolve(computer).clone();
+        let result = from.resolve(computer)?;
         computer.output(&result);
       }
       OpCode::RelativeAdjustment(adjustment) => {
-        computer.relative_base = computer.calc_relative(adjustment.resolve(computer) as i32);
+        let adjustment = adjustment.resolve(computer)?;
+        computer.relative_base = CheckedAdd::checked_add(&computer.relative_base, &adjustment)
+          .ok_or(ComputerError::Overflow {
+            address: computer.instruction_pointer,
+          })?;
       }
-      OpCode::Done => {}
+      OpCode::Done => return Ok(()),
     }
+
+    computer.instruction_pointer += self.size();
+    Ok(())
   }
 }
@@ -0,0 +1,147 @@
+use num::Zero;
+
+use super::{Computer, ComputerError, ComputerWord, Status};
+
+/// A chain of computers where each one's output is fed to the next one's input.
+///
+/// With feedback enabled the last computer's output loops back around to the
+/// first, and the chain keeps running until nothing is left to move.
+pub struct Pipeline {
+  computers: Vec<Computer>,
+  feedback: bool,
+}
+
+impl Pipeline {
+  /// Boots one computer per phase setting, each given its phase as the first input.
+  pub fn new(program: &[ComputerWord], phases: &[ComputerWord]) -> Self {
+    let computers = phases
+      .iter()
+      .map(|phase| {
+        let mut computer = Computer::new(program.to_vec());
+        computer.push_input(phase.clone());
+        computer
+      })
+      .collect();
+
+    Pipeline {
+      computers,
+      feedback: false,
+    }
+  }
+
+  pub fn with_feedback(mut self) -> Self {
+    self.feedback = true;
+    self
+  }
+
+  /// Sends `signal` into the first computer and returns the last signal
+  /// the final computer produced.
+  pub fn run(mut self, signal: ComputerWord) -> Result<Option<ComputerWord>, ComputerError> {
+    let count = self.computers.len();
+    if count == 0 {
+      return Ok(None);
+    }
+
+    self.computers[0].push_input(signal);
+
+    let mut last_signal = None;
+    loop {
+      let mut progressed = false;
+
+      for idx in 0..count {
+        while let Status::Output(value) = self.computers[idx].resume()? {
+          progressed = true;
+
+          if idx + 1 < count {
+            self.computers[idx + 1].push_input(value);
+          } else {
+            if self.feedback {
+              self.computers[0].push_input(value.clone());
+            }
+            last_signal = Some(value);
+          }
+        }
+      }
+
+      // Every computer is either halted or waiting on input nobody will send.
+      if !progressed {
+        return Ok(last_signal);
+      }
+    }
+  }
+}
+
+/// Tries every ordering of `phases` and returns the best signal with the phases that produced it.
+pub fn max_signal(
+  program: &[ComputerWord],
+  phases: &[ComputerWord],
+  feedback: bool,
+) -> Result<Option<(ComputerWord, Vec<ComputerWord>)>, ComputerError> {
+  let mut best = None;
+  for ordering in permutations(phases) {
+    let pipeline = Pipeline::new(program, &ordering);
+    let pipeline = if feedback {
+      pipeline.with_feedback()
+    } else {
+      pipeline
+    };
+
+    if let Some(signal) = pipeline.run(ComputerWord::zero())? {
+      let is_better = match &best {
+        Some((best_signal, _)) => signal > *best_signal,
+        None => true,
+      };
+      if is_better {
+        best = Some((signal, ordering));
+      }
+    }
+  }
+  Ok(best)
+}
+
+fn permutations(values: &[ComputerWord]) -> Vec<Vec<ComputerWord>> {
+  if values.len() <= 1 {
+    return vec![values.to_vec()];
+  }
+
+  (0..values.len())
+    .flat_map(|idx| {
+      let mut rest = values.to_vec();
+      let first = rest.remove(idx);
+      permutations(&rest).into_iter().map(move |mut tail| {
+        tail.insert(0, first.clone());
+        tail
+      })
+    })
+    .collect()
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  #[test]
+  fn test_permutations() {
+    let all = permutations(&[1, 2, 3]);
+    assert_eq!(
+      all,
+      vec![
+        vec![1, 2, 3],
+        vec![1, 3, 2],
+        vec![2, 1, 3],
+        vec![2, 3, 1],
+        vec![3, 1, 2],
+        vec![3, 2, 1],
+      ]
+    );
+  }
+
+  #[test]
+  fn test_passes_signal_through() {
+    // Outputs its phase plus its input.
+    let program = parse_op_stack("3,11,3,12,1,11,12,11,4,11,99,0,0").unwrap();
+    let pipeline = Pipeline::new(&program, &[1, 2, 3]);
+    assert_eq!(pipeline.run(10), Ok(Some(16)));
+  }
+}
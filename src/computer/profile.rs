@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::disassembler::disassemble;
+use super::{ComputerState, OpCode};
+
+/// How often each instruction ran, for a [`super::Computer`] built with
+/// [`super::Computer::with_profile`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Profile {
+  total: u64,
+  hits: BTreeMap<usize, u64>,
+  /// Keyed by mnemonic, so every `add` counts together whatever its modes.
+  op_codes: BTreeMap<&'static str, u64>,
+}
+
+/// How much of a program's code ran at least once.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Coverage {
+  pub covered: usize,
+  pub instructions: usize,
+}
+
+impl Profile {
+  pub fn new() -> Self {
+    Profile::default()
+  }
+
+  pub(super) fn record(&mut self, address: usize, op_code: &OpCode) {
+    self.total += 1;
+    *self.hits.entry(address).or_insert(0) += 1;
+    *self.op_codes.entry(op_code.mnemonic()).or_insert(0) += 1;
+  }
+
+  /// Instructions executed in all.
+  pub fn total(&self) -> u64 {
+    self.total
+  }
+
+  /// How many times the instruction at `address` ran.
+  pub fn hits(&self, address: usize) -> u64 {
+    self.hits.get(&address).copied().unwrap_or(0)
+  }
+
+  /// Executions per op code mnemonic.
+  pub fn op_codes(&self) -> &BTreeMap<&'static str, u64> {
+    &self.op_codes
+  }
+
+  /// The addresses that ran most often, busiest first.
+  pub fn hot_spots(&self, count: usize) -> Vec<(usize, u64)> {
+    let mut hits: Vec<(usize, u64)> = self.hits.iter().map(|(&a, &h)| (a, h)).collect();
+    hits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    hits.truncate(count);
+    hits
+  }
+
+  /// Covered instructions out of all those in a linear disassembly of
+  /// `program`.
+  ///
+  /// Data that happens to decode counts as an instruction, so treat this as
+  /// a lower bound. Words that only became instructions at run time count
+  /// as covered.
+  pub fn coverage(&self, program: &ComputerState) -> Coverage {
+    let lines = disassemble(program);
+    Coverage {
+      covered: lines
+        .iter()
+        .filter(|line| self.hits(line.address) > 0)
+        .count(),
+      instructions: lines
+        .iter()
+        .filter(|line| line.op_code.is_some() || self.hits(line.address) > 0)
+        .count(),
+    }
+  }
+
+  /// A listing of `program` with each line prefixed by its hit count.
+  ///
+  /// Instructions that never ran are marked `#####` and data is marked `-`,
+  /// as `gcov` does.
+  pub fn annotated_listing(&self, program: &ComputerState) -> String {
+    let mut listing = String::new();
+    for line in disassemble(program) {
+      let hits = match (self.hits(line.address), &line.op_code) {
+        (0, Some(_)) => "#####".to_string(),
+        (0, None) => "-".to_string(),
+        (hits, _) => hits.to_string(),
+      };
+      let _ = writeln!(listing, "{:>9}: {}", hits, line);
+    }
+    listing
+  }
+
+  /// Totals, coverage and executions per op code.
+  pub fn summary(&self, program: &ComputerState) -> String {
+    let coverage = self.coverage(program);
+    let mut summary = format!(
+      "{} instructions executed\n{} of {} instructions covered ({:.1}%)\n",
+      self.total,
+      coverage.covered,
+      coverage.instructions,
+      coverage.percent()
+    );
+    for (mnemonic, count) in &self.op_codes {
+      let _ = writeln!(summary, "{:>5} {}", mnemonic, count);
+    }
+    summary
+  }
+}
+
+impl Coverage {
+  pub fn percent(&self) -> f64 {
+    match self.instructions {
+      0 => 100.0,
+      instructions => 100.0 * self.covered as f64 / instructions as f64,
+    }
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::{parse_op_stack, Computer, Status, Trace};
+
+  // Outputs 1 if the input is 8 and 0 otherwise, jumping over the unused branch.
+  const IS_EIGHT: &str = "3,3,1108,-1,8,3,1005,3,12,104,0,99,104,1,99";
+
+  fn profile(input: i64) -> (Profile, ComputerState) {
+    let program = parse_op_stack(IS_EIGHT).unwrap();
+    let mut computer = Computer::new(program.clone())
+      .with_profile(Profile::new())
+      .add_input(input);
+    computer.eval().unwrap();
+    (
+      computer.take_profile().unwrap(),
+      ComputerState::from(program),
+    )
+  }
+
+  #[test]
+  fn test_counts() {
+    let (profile, _) = profile(8);
+    assert_eq!(profile.total(), 5);
+    assert_eq!(profile.hits(0), 1);
+    assert_eq!(profile.hits(9), 0);
+    assert_eq!(profile.op_codes().get("eq"), Some(&1));
+    assert_eq!(profile.op_codes().get("out"), Some(&1));
+    assert_eq!(profile.hot_spots(1), vec![(0, 1)]);
+
+    // Halting by resuming counts the same as running to completion.
+    let mut computer = Computer::new(parse_op_stack(IS_EIGHT).unwrap())
+      .with_profile(Profile::new())
+      .add_input(8);
+    while computer.resume().unwrap() != Status::Halted {}
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+    assert_eq!(computer.profile(), Some(&profile));
+    assert_eq!(profile.op_codes().get("hlt"), Some(&1));
+  }
+
+  #[test]
+  fn test_agrees_with_trace() {
+    let mut computer = Computer::new(parse_op_stack(IS_EIGHT).unwrap())
+      .with_profile(Profile::new())
+      .with_trace(Trace::new())
+      .add_input(7);
+    while computer.resume().unwrap() != Status::Halted {}
+    assert_eq!(computer.resume(), Ok(Status::Halted));
+
+    let trace = computer.take_trace().unwrap();
+    let profile = computer.take_profile().unwrap();
+    assert_eq!(trace.entries().len() as u64, profile.total());
+    assert_eq!(trace.entries().last().unwrap().op_code, OpCode::Done);
+  }
+
+  #[test]
+  fn test_coverage() {
+    let (eight, program) = profile(8);
+    let (seven, _) = profile(7);
+    assert_eq!(
+      eight.coverage(&program),
+      Coverage {
+        covered: 5,
+        instructions: 7
+      }
+    );
+    assert_eq!(seven.coverage(&program).covered, 5);
+
+    let listing = eight.annotated_listing(&program);
+    let lines: Vec<&str> = listing.lines().map(str::trim_end).collect();
+    assert!(lines[0].starts_with("        1: 0000: in -> @3 "));
+    assert!(lines[3].starts_with("    #####: 0009: out 0 "));
+    assert!(eight
+      .summary(&program)
+      .starts_with("5 instructions executed\n5 of 7 instructions covered (71.4%)\n"));
+  }
+}
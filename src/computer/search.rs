@@ -0,0 +1,177 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::{words, Computer, ComputerError, ComputerState, ComputerWord};
+
+/// One job per CPU, or just the one if that can't be worked out.
+pub fn default_jobs() -> usize {
+  thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// Tries every combination of values at a handful of addresses in a program,
+/// keeping the combinations whose finished run passes a predicate.
+///
+/// Each combination boots a fresh computer from the base memory with the
+/// values written in, and runs it to completion without any input.
+/// Combinations are spread across threads.
+pub struct PatchSearch {
+  base: ComputerState,
+  patches: Vec<(usize, Vec<ComputerWord>)>,
+  jobs: usize,
+}
+
+impl PatchSearch {
+  pub fn new(base: ComputerState) -> Self {
+    PatchSearch {
+      base,
+      patches: Vec::new(),
+      jobs: default_jobs(),
+    }
+  }
+
+  /// Tries each of `values` at `address`.
+  pub fn vary(mut self, address: usize, values: RangeInclusive<i64>) -> Self {
+    self.patches.push((address, words(values)));
+    self
+  }
+
+  /// Caps how many threads the search uses; one per CPU by default.
+  pub fn jobs(mut self, jobs: usize) -> Self {
+    self.jobs = jobs.max(1);
+    self
+  }
+
+  /// How many combinations there are to try.
+  pub fn len(&self) -> usize {
+    self
+      .patches
+      .iter()
+      .map(|(_, values)| values.len())
+      .product()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The `index`th combination, counting with the last address varying fastest.
+  fn assignment(&self, mut index: usize) -> Vec<ComputerWord> {
+    let mut assignment = vec![ComputerWord::default(); self.patches.len()];
+    for (slot, (_, values)) in assignment.iter_mut().zip(&self.patches).rev() {
+      *slot = values[index % values.len()].clone();
+      index /= values.len();
+    }
+    assignment
+  }
+
+  fn try_assignment(
+    &self,
+    assignment: &[ComputerWord],
+    predicate: &impl Fn(&Computer) -> bool,
+  ) -> Result<bool, ComputerError> {
+    let mut state = self.base.clone();
+    for ((address, _), value) in self.patches.iter().zip(assignment) {
+      state.set(*address, value.clone());
+    }
+    let mut computer = Computer::from(state);
+    computer.interpret()?;
+    Ok(predicate(&computer))
+  }
+
+  /// Returns every combination whose run satisfies `predicate`, in the order
+  /// they'd be tried one at a time, as values for the addresses in the order
+  /// they were added with [`PatchSearch::vary`].
+  ///
+  /// A combination whose run fails doesn't match. If nothing matches, the
+  /// error from the first combination (in that same order) to fail is
+  /// returned instead.
+  pub fn run(
+    &self,
+    predicate: impl Fn(&Computer) -> bool + Sync,
+  ) -> Result<Vec<Vec<ComputerWord>>, ComputerError> {
+    let total = self.len();
+    let next = AtomicUsize::new(0);
+    let matches = Mutex::new(Vec::new());
+    let first_error = Mutex::new(None);
+
+    thread::scope(|scope| {
+      for _ in 0..self.jobs.min(total.max(1)) {
+        scope.spawn(|| loop {
+          let index = next.fetch_add(1, Ordering::Relaxed);
+          if index >= total {
+            break;
+          }
+          let assignment = self.assignment(index);
+          match self.try_assignment(&assignment, &predicate) {
+            Ok(true) => matches.lock().unwrap().push((index, assignment)),
+            Ok(false) => {}
+            Err(error) => {
+              let mut first_error = first_error.lock().unwrap();
+              if first_error.as_ref().is_none_or(|(first, _)| index < *first) {
+                *first_error = Some((index, error));
+              }
+            }
+          }
+        });
+      }
+    });
+
+    let mut matches = matches.into_inner().unwrap();
+    if matches.is_empty() {
+      if let Some((_, error)) = first_error.into_inner().unwrap() {
+        return Err(error);
+      }
+    }
+    matches.sort_by_key(|(index, _)| *index);
+    Ok(
+      matches
+        .into_iter()
+        .map(|(_, assignment)| assignment)
+        .collect(),
+    )
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  fn base(program: &str) -> ComputerState {
+    ComputerState::from(parse_op_stack(program).unwrap())
+  }
+
+  #[test]
+  fn test_finds_every_match_in_order() {
+    // Adds the words at the addresses held in 1 and 2, storing the sum in 0.
+    let search = PatchSearch::new(base("1,0,0,0,99,10,20,30"))
+      .vary(1, 5..=7)
+      .vary(2, 5..=7)
+      .jobs(3);
+    assert_eq!(search.len(), 9);
+    assert_eq!(
+      search.run(|computer| computer.resolve(0) == 40),
+      Ok(vec![vec![5, 7], vec![6, 6], vec![7, 5]])
+    );
+    assert_eq!(search.run(|computer| computer.resolve(0) == 0), Ok(vec![]));
+  }
+
+  #[test]
+  fn test_errors_only_fail_a_search_without_matches() {
+    // Address 6 holds an invalid op code, which the jump at 0 lands on
+    // when address 1 is zero; otherwise it falls through to the 99.
+    let search = PatchSearch::new(base("1106,0,6,99,0,0,42"))
+      .vary(1, 0..=3)
+      .jobs(2);
+    assert_eq!(search.run(|_| true), Ok(vec![vec![1], vec![2], vec![3]]));
+    assert_eq!(
+      search.run(|_| false),
+      Err(ComputerError::InvalidOpCode {
+        op_code: 42,
+        address: 6
+      })
+    );
+  }
+}
@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use super::{Computer, ComputerError, ComputerState, ComputerWord};
+
+const HEADER: &str = "intcode snapshot v1";
+
+fn join(words: impl IntoIterator<Item = ComputerWord>) -> String {
+  let words: Vec<String> = words.into_iter().map(|w| w.to_string()).collect();
+  words.join(",")
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> ComputerError {
+  ComputerError::InvalidSnapshot {
+    line,
+    message: message.into(),
+  }
+}
+
+fn parse<T: FromStr>(line: usize, text: &str) -> Result<T, ComputerError> {
+  text
+    .parse()
+    .map_err(|_| invalid(line, format!("can't parse '{}'", text)))
+}
+
+fn parse_words(line: usize, text: &str) -> Result<Vec<ComputerWord>, ComputerError> {
+  if text.is_empty() {
+    return Ok(vec![]);
+  }
+  text
+    .split(',')
+    .map(|word| parse(line, word.trim()))
+    .collect()
+}
+
+/// Reads the `key value` pairs of a snapshot in order, tracking line numbers for errors.
+struct Fields<'a> {
+  lines: std::iter::Enumerate<std::str::Lines<'a>>,
+  line: usize,
+}
+
+impl<'a> Fields<'a> {
+  fn next_line(&mut self) -> Option<&'a str> {
+    let (index, text) = self.lines.next()?;
+    self.line = index + 1;
+    Some(text)
+  }
+
+  fn expect(&mut self, key: &str) -> Result<&'a str, ComputerError> {
+    let text = self
+      .next_line()
+      .ok_or_else(|| invalid(self.line + 1, format!("missing '{}'", key)))?;
+    let mut parts = text.splitn(2, ' ');
+    if parts.next() != Some(key) {
+      return Err(invalid(self.line, format!("expected '{}'", key)));
+    }
+    Ok(parts.next().unwrap_or("").trim())
+  }
+
+  fn value<T: FromStr>(&mut self, key: &str) -> Result<T, ComputerError> {
+    let text = self.expect(key)?;
+    parse(self.line, text)
+  }
+
+  fn words(&mut self, key: &str) -> Result<Vec<ComputerWord>, ComputerError> {
+    let text = self.expect(key)?;
+    parse_words(self.line, text)
+  }
+}
+
+impl Computer {
+  /// Saves the complete machine state as text that [`Computer::restore`] reads back.
+  ///
+  /// The format is line based: a header, the registers, pending input and
+  /// output so far, then memory a page at a time with leading and trailing zeroes left out.
+  /// Tracing isn't part of the snapshot. See [`Computer::snapshot_bytes`]
+  /// for a much smaller binary version.
+  pub fn snapshot(&self) -> String {
+    let mut out = Vec::new();
+    self
+      .write_snapshot(&mut out)
+      .expect("Writing to a Vec can't fail");
+    String::from_utf8(out).expect("Snapshots are ASCII")
+  }
+
+  pub fn write_snapshot(&self, mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "{}", HEADER)?;
+    writeln!(out, "ip {}", self.instruction_pointer)?;
+    writeln!(out, "rb {}", self.relative_base)?;
+    writeln!(out, "input {}", join(self.input.iter().cloned()))?;
+    writeln!(out, "output {}", join(self.output.iter().cloned()))?;
+    writeln!(out, "len {}", self.state.len())?;
+    for (start, words) in self.state.runs() {
+      writeln!(out, "@{} {}", start, join(words.iter().cloned()))?;
+    }
+    Ok(())
+  }
+
+  /// Rebuilds a computer from [`Computer::snapshot`], ready to carry on where it was saved.
+  pub fn restore(snapshot: &str) -> Result<Computer, ComputerError> {
+    let mut fields = Fields {
+      lines: snapshot.lines().enumerate(),
+      line: 0,
+    };
+
+    if fields.next_line() != Some(HEADER) {
+      return Err(invalid(1, format!("expected '{}'", HEADER)));
+    }
+    let instruction_pointer = fields.value("ip")?;
+    let relative_base = fields.value("rb")?;
+    let input = fields.words("input")?;
+    let output = fields.words("output")?;
+    let len = fields.value("len")?;
+
+    let mut state = ComputerState::new();
+    while let Some(text) = fields.next_line() {
+      let line = fields.line;
+      if text.trim().is_empty() {
+        continue;
+      }
+      let (start, words) = match text.strip_prefix('@').map(|rest| rest.split_once(' ')) {
+        Some(Some((start, words))) => (parse::<usize>(line, start)?, parse_words(line, words)?),
+        _ => return Err(invalid(line, "expected '@address words'")),
+      };
+      for (offset, word) in words.into_iter().enumerate() {
+        match start.checked_add(offset) {
+          Some(address) if address < len => state.set(address, word),
+          _ => return Err(invalid(line, "memory runs past 'len'")),
+        }
+      }
+    }
+    state.grow_to(len);
+
+    Ok(Computer {
+      input: VecDeque::from(input),
+      output,
+      instruction_pointer,
+      relative_base,
+      ..Computer::from(state)
+    })
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::{from, Status, ECHO};
+
+  #[test]
+  fn test_fork() {
+    let mut computer = from(ECHO).unwrap().add_input(3);
+    assert_eq!(computer.resume(), Ok(Status::Output(3)));
+
+    let mut fork = computer.clone();
+    fork.push_input(0);
+    assert_eq!(fork.resume(), Ok(Status::Output(0)));
+    assert_eq!(fork.resume(), Ok(Status::Halted));
+
+    assert_eq!(computer.resume(), Ok(Status::NeedsInput));
+    computer.push_input(4);
+    assert_eq!(computer.resume(), Ok(Status::Output(4)));
+  }
+
+  #[test]
+  fn test_snapshot_round_trip() {
+    // Sets rb to 7, writes 3 to 5007, then echoes an input through @20.
+    let mut computer = from("109,7,21101,1,2,5000,3,20,4,20,99")
+      .unwrap()
+      .add_input(8)
+      .add_input(9);
+    assert_eq!(computer.resume(), Ok(Status::Output(8)));
+    computer.output(&1);
+
+    let snapshot = computer.snapshot();
+    assert_eq!(
+      snapshot,
+      "intcode snapshot v1\n\
+       ip 10\n\
+       rb 7\n\
+       input 9\n\
+       output 1\n\
+       len 5008\n\
+       @0 109,7,21101,1,2,5000,3,20,4,20,99,0,0,0,0,0,0,0,0,0,8\n\
+       @5007 3\n"
+    );
+
+    let mut restored = Computer::restore(&snapshot).unwrap();
+    assert_eq!(restored.snapshot(), snapshot);
+    assert_eq!(restored.resolve(5007), 3);
+    assert_eq!(restored.resume(), Ok(Status::Halted));
+    assert_eq!(restored.run(), Ok(vec![1]));
+  }
+
+  #[test]
+  fn test_invalid_snapshot() {
+    let error = |message: &str, line| {
+      Err(ComputerError::InvalidSnapshot {
+        line,
+        message: message.to_string(),
+      })
+    };
+
+    assert_eq!(
+      Computer::restore("intcode v0").map(|_| ()),
+      error("expected 'intcode snapshot v1'", 1)
+    );
+    assert_eq!(
+      Computer::restore("intcode snapshot v1\nip 0\nrb x").map(|_| ()),
+      error("can't parse 'x'", 3)
+    );
+    assert_eq!(
+      Computer::restore("intcode snapshot v1\nip 0\nrb 0\ninput\noutput").map(|_| ()),
+      error("missing 'len'", 6)
+    );
+    assert_eq!(
+      Computer::restore("intcode snapshot v1\nip 0\nrb 0\ninput\noutput\nlen 1\n@0 1,2")
+        .map(|_| ()),
+      error("memory runs past 'len'", 7)
+    );
+    assert_eq!(
+      Computer::restore(&format!(
+        "intcode snapshot v1\nip 0\nrb 0\ninput\noutput\nlen {0}\n@{0} 1,2",
+        usize::MAX
+      ))
+      .map(|_| ()),
+      error("memory runs past 'len'", 7)
+    );
+  }
+}
@@ -0,0 +1,643 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Error, Formatter};
+use std::ops::RangeInclusive;
+
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+use num::Integer;
+
+use super::{
+  ComputerError, ComputerState, ComputerWord, Instruction, InstructionSize, OpArg, OpCode,
+};
+
+/// A word of memory whose value may depend on symbols.
+///
+/// Built up as the program runs, folding constants as it goes, so only the
+/// parts that really depend on a symbol are left as trees.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expr {
+  Const(ComputerWord),
+  Symbol(String),
+  Add(Box<Expr>, Box<Expr>),
+  Mul(Box<Expr>, Box<Expr>),
+  LessThan(Box<Expr>, Box<Expr>),
+  Equals(Box<Expr>, Box<Expr>),
+  /// Whatever is in memory at a symbolic address.
+  Load(Box<Expr>),
+}
+
+impl Expr {
+  pub fn symbol(name: &str) -> Expr {
+    Expr::Symbol(name.to_string())
+  }
+
+  fn add(a: Expr, b: Expr) -> Option<Expr> {
+    Some(match (a, b) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const(CheckedAdd::checked_add(&a, &b)?),
+      (Expr::Const(zero), other) | (other, Expr::Const(zero)) if zero.is_zero() => other,
+      (a, b) => Expr::Add(Box::new(a), Box::new(b)),
+    })
+  }
+
+  fn mul(a: Expr, b: Expr) -> Option<Expr> {
+    Some(match (a, b) {
+      (Expr::Const(a), Expr::Const(b)) => Expr::Const(CheckedMul::checked_mul(&a, &b)?),
+      (Expr::Const(zero), _) | (_, Expr::Const(zero)) if zero.is_zero() => Expr::Const(zero),
+      (Expr::Const(one), other) | (other, Expr::Const(one)) if one.is_one() => other,
+      (a, b) => Expr::Mul(Box::new(a), Box::new(b)),
+    })
+  }
+
+  fn compare(
+    a: Expr,
+    b: Expr,
+    condition: impl Fn(&ComputerWord, &ComputerWord) -> bool,
+    node: impl Fn(Box<Expr>, Box<Expr>) -> Expr,
+  ) -> Expr {
+    match (a, b) {
+      (Expr::Const(a), Expr::Const(b)) if condition(&a, &b) => Expr::Const(ComputerWord::one()),
+      (Expr::Const(_), Expr::Const(_)) => Expr::Const(ComputerWord::zero()),
+      (a, b) => node(Box::new(a), Box::new(b)),
+    }
+  }
+
+  /// The expression as `constant + Σ coefficient × symbol`, if it is one.
+  ///
+  /// `None` for anything that multiplies symbols together, compares them,
+  /// or reads memory at a symbolic address, and for coefficients that
+  /// overflow.
+  pub fn linear(&self) -> Option<Linear> {
+    match self {
+      Expr::Const(value) => Some(Linear {
+        constant: value.clone(),
+        terms: BTreeMap::new(),
+      }),
+      Expr::Symbol(name) => Some(Linear {
+        constant: ComputerWord::zero(),
+        terms: vec![(name.clone(), ComputerWord::one())]
+          .into_iter()
+          .collect(),
+      }),
+      Expr::Add(a, b) => a.linear()?.add(&b.linear()?),
+      Expr::Mul(a, b) => {
+        let (a, b) = (a.linear()?, b.linear()?);
+        match (a.terms.is_empty(), b.terms.is_empty()) {
+          (true, _) => b.scale(&a.constant),
+          (_, true) => a.scale(&b.constant),
+          _ => None,
+        }
+      }
+      Expr::LessThan(..) | Expr::Equals(..) | Expr::Load(_) => None,
+    }
+  }
+
+  fn fmt_operand(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    match self {
+      Expr::Add(..) => write!(f, "({})", self),
+      _ => write!(f, "{}", self),
+    }
+  }
+}
+
+impl Display for Expr {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    match self {
+      Expr::Const(value) => write!(f, "{}", value),
+      Expr::Symbol(name) => write!(f, "{}", name),
+      Expr::Add(a, b) => write!(f, "{} + {}", a, b),
+      Expr::Mul(a, b) => {
+        a.fmt_operand(f)?;
+        write!(f, " * ")?;
+        b.fmt_operand(f)
+      }
+      Expr::LessThan(a, b) => write!(f, "({} < {})", a, b),
+      Expr::Equals(a, b) => write!(f, "({} == {})", a, b),
+      Expr::Load(address) => write!(f, "mem[{}]", address),
+    }
+  }
+}
+
+/// A linear expression: `constant` plus each symbol times its coefficient.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Linear {
+  pub constant: ComputerWord,
+  /// Never holds a zero coefficient.
+  pub terms: BTreeMap<String, ComputerWord>,
+}
+
+impl Linear {
+  fn add(mut self, other: &Linear) -> Option<Linear> {
+    self.constant = CheckedAdd::checked_add(&self.constant, &other.constant)?;
+    for (name, coefficient) in &other.terms {
+      let sum = match self.terms.get(name) {
+        Some(existing) => CheckedAdd::checked_add(existing, coefficient)?,
+        None => coefficient.clone(),
+      };
+      if sum.is_zero() {
+        self.terms.remove(name);
+      } else {
+        self.terms.insert(name.clone(), sum);
+      }
+    }
+    Some(self)
+  }
+
+  fn scale(mut self, factor: &ComputerWord) -> Option<Linear> {
+    if factor.is_zero() {
+      self.terms.clear();
+    }
+    self.constant = CheckedMul::checked_mul(&self.constant, factor)?;
+    for coefficient in self.terms.values_mut() {
+      *coefficient = CheckedMul::checked_mul(&*coefficient, factor)?;
+    }
+    Some(self)
+  }
+
+  fn value(&self, values: &BTreeMap<&str, ComputerWord>) -> Option<ComputerWord> {
+    self
+      .terms
+      .iter()
+      .try_fold(self.constant.clone(), |sum, (name, coefficient)| {
+        let term = CheckedMul::checked_mul(coefficient, values.get(name.as_str())?)?;
+        CheckedAdd::checked_add(&sum, &term)
+      })
+  }
+
+  /// Finds values within `bounds` for which the expression equals `target`,
+  /// returning them in the same order as `bounds`.
+  ///
+  /// The last bounded symbol that appears in the expression is solved for
+  /// directly, so only the others are enumerated, in order; the first
+  /// solution found is returned. Symbols that don't appear take the start
+  /// of their range. Every symbol in the expression needs a bound.
+  pub fn solve(
+    &self,
+    target: &ComputerWord,
+    bounds: &[(&str, RangeInclusive<i64>)],
+  ) -> Result<Option<Vec<ComputerWord>>, SymbolicError> {
+    if let Some(name) = self
+      .terms
+      .keys()
+      .find(|name| !bounds.iter().any(|(bound, _)| bound == name))
+    {
+      return Err(SymbolicError::Unbounded(name.clone()));
+    }
+    let solved = bounds
+      .iter()
+      .rposition(|(name, _)| self.terms.contains_key(*name));
+
+    let mut values: BTreeMap<&str, ComputerWord> = bounds
+      .iter()
+      .map(|(name, range)| (*name, ComputerWord::from(*range.start())))
+      .collect();
+    let enumerated: Vec<(&str, &RangeInclusive<i64>)> = bounds
+      .iter()
+      .enumerate()
+      .filter(|&(index, (name, _))| Some(index) != solved && self.terms.contains_key(*name))
+      .map(|(_, (name, range))| (*name, range))
+      .collect();
+
+    loop {
+      if let Some(value) = self.solve_one(target, solved.map(|index| &bounds[index]), &values) {
+        if let Some(index) = solved {
+          values.insert(bounds[index].0, value);
+        }
+        return Ok(Some(
+          bounds
+            .iter()
+            .map(|(name, _)| values[name].clone())
+            .collect(),
+        ));
+      }
+
+      // Step the enumerated symbols like an odometer, the last one fastest.
+      let mut carried = true;
+      for (name, range) in enumerated.iter().rev() {
+        let next = values[name].clone() + ComputerWord::one();
+        if next <= ComputerWord::from(*range.end()) {
+          values.insert(name, next);
+          carried = false;
+          break;
+        }
+        values.insert(name, ComputerWord::from(*range.start()));
+      }
+      if carried {
+        return Ok(None);
+      }
+    }
+  }
+
+  /// With every other symbol fixed by `values`, the value of `solved` (if
+  /// any) within its range that makes the expression hit `target`.
+  fn solve_one(
+    &self,
+    target: &ComputerWord,
+    solved: Option<&(&str, RangeInclusive<i64>)>,
+    values: &BTreeMap<&str, ComputerWord>,
+  ) -> Option<ComputerWord> {
+    let (name, range) = match solved {
+      Some(solved) => solved,
+      None => {
+        return Some(ComputerWord::zero()).filter(|_| self.value(values).as_ref() == Some(target))
+      }
+    };
+
+    let mut values = values.clone();
+    values.insert(name, ComputerWord::zero());
+    let remainder = CheckedSub::checked_sub(target, &self.value(&values)?)?;
+    let coefficient = &self.terms[*name];
+    let (value, leftover) = remainder.div_rem(coefficient);
+    if !leftover.is_zero() {
+      return None;
+    }
+    let in_range =
+      ComputerWord::from(*range.start()) <= value && value <= ComputerWord::from(*range.end());
+    Some(value).filter(|_| in_range)
+  }
+}
+
+impl Display for Linear {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    let mut first = true;
+    for (name, coefficient) in &self.terms {
+      let negative = coefficient < &ComputerWord::zero();
+      match (first, negative) {
+        (true, true) => write!(f, "-")?,
+        (false, true) => write!(f, " - ")?,
+        (false, false) => write!(f, " + ")?,
+        (true, false) => {}
+      }
+      let magnitude = if negative {
+        -coefficient.clone()
+      } else {
+        coefficient.clone()
+      };
+      if magnitude.is_one() {
+        write!(f, "{}", name)?;
+      } else {
+        write!(f, "{} * {}", magnitude, name)?;
+      }
+      first = false;
+    }
+
+    let zero = ComputerWord::zero();
+    match (first, self.constant < zero) {
+      (true, _) => write!(f, "{}", self.constant),
+      (false, _) if self.constant.is_zero() => Ok(()),
+      (false, true) => write!(f, " - {}", -self.constant.clone()),
+      (false, false) => write!(f, " + {}", self.constant),
+    }
+  }
+}
+
+/// Why a program couldn't be evaluated symbolically.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SymbolicError {
+  /// The program failed the same way it would have on a [`super::Computer`].
+  Computer(ComputerError),
+  /// Whether a jump is taken depends on a symbol.
+  SymbolicBranch { address: usize, condition: Expr },
+  /// Where a jump goes depends on a symbol.
+  SymbolicJump { address: usize, target: Expr },
+  /// The instruction to run next depends on a symbol.
+  SymbolicInstruction { address: usize },
+  /// Where a result is written depends on a symbol.
+  SymbolicWrite { address: usize, destination: Expr },
+  /// The relative base would depend on a symbol.
+  SymbolicRelativeBase { address: usize, adjustment: Expr },
+  /// A symbol in the expression has no range to solve within.
+  Unbounded(String),
+}
+
+impl Display for SymbolicError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    match self {
+      SymbolicError::Computer(error) => write!(f, "{}", error),
+      SymbolicError::SymbolicBranch { address, condition } => {
+        write!(f, "Branch depends on a symbol: {} @{}", condition, address)
+      }
+      SymbolicError::SymbolicJump { address, target } => {
+        write!(
+          f,
+          "Jump target depends on a symbol: {} @{}",
+          target, address
+        )
+      }
+      SymbolicError::SymbolicInstruction { address } => {
+        write!(f, "Instruction depends on a symbol @{}", address)
+      }
+      SymbolicError::SymbolicWrite {
+        address,
+        destination,
+      } => write!(
+        f,
+        "Write to a symbolic address: {} @{}",
+        destination, address
+      ),
+      SymbolicError::SymbolicRelativeBase {
+        address,
+        adjustment,
+      } => write!(
+        f,
+        "Relative base depends on a symbol: {} @{}",
+        adjustment, address
+      ),
+      SymbolicError::Unbounded(name) => write!(f, "No bounds given for '{}'", name),
+    }
+  }
+}
+
+impl std::error::Error for SymbolicError {}
+
+impl From<ComputerError> for SymbolicError {
+  fn from(error: ComputerError) -> Self {
+    SymbolicError::Computer(error)
+  }
+}
+
+/// Runs a program with some memory cells standing in for unknowns.
+///
+/// Control flow has to stay concrete: the evaluation stops with an error as
+/// soon as a jump, a write address or the next instruction depends on a
+/// symbol. Input reads produce fresh symbols named `input0`, `input1`, ...
+pub struct Symbolic {
+  /// Concrete memory; cells in `symbols` shadow whatever is stored here.
+  state: ComputerState,
+  symbols: HashMap<usize, Expr>,
+  instruction_pointer: usize,
+  relative_base: ComputerWord,
+  inputs: usize,
+  outputs: Vec<Expr>,
+}
+
+impl Symbolic {
+  pub fn new(state: ComputerState) -> Self {
+    Symbolic {
+      state,
+      symbols: HashMap::new(),
+      instruction_pointer: 0,
+      relative_base: ComputerWord::zero(),
+      inputs: 0,
+      outputs: Vec::new(),
+    }
+  }
+
+  /// Replaces the word at `address` with the symbol `name`.
+  pub fn with_symbol(mut self, address: usize, name: &str) -> Self {
+    self.set(address, Expr::symbol(name));
+    self
+  }
+
+  pub fn get(&self, address: usize) -> Expr {
+    match self.symbols.get(&address) {
+      Some(expr) => expr.clone(),
+      None => Expr::Const(self.state.get(address)),
+    }
+  }
+
+  pub fn outputs(&self) -> &[Expr] {
+    &self.outputs
+  }
+
+  fn set(&mut self, address: usize, value: Expr) {
+    match value {
+      Expr::Const(value) => {
+        self.symbols.remove(&address);
+        self.state.set(address, value);
+      }
+      value => {
+        self.symbols.insert(address, value);
+        self.state.grow_to(address + 1);
+      }
+    }
+  }
+
+  fn load(&self, address: Expr) -> Result<Expr, SymbolicError> {
+    match address {
+      Expr::Const(address) => Ok(self.get(ComputerState::address(&address)?)),
+      address => Ok(Expr::Load(Box::new(address))),
+    }
+  }
+
+  fn relative(&self, offset: Expr) -> Result<Expr, SymbolicError> {
+    Expr::add(Expr::Const(self.relative_base.clone()), offset).ok_or_else(|| self.overflow())
+  }
+
+  fn overflow(&self) -> SymbolicError {
+    SymbolicError::Computer(ComputerError::Overflow {
+      address: self.instruction_pointer,
+    })
+  }
+
+  /// The value of the `index`th (1-based) argument of the current instruction.
+  fn operand(&self, arg: &OpArg, index: usize) -> Result<Expr, SymbolicError> {
+    let word = self.get(self.instruction_pointer + index);
+    match arg {
+      OpArg::Literal(_) => Ok(word),
+      OpArg::Reference(_) => self.load(word),
+      OpArg::Relative(_) => self.load(self.relative(word)?),
+    }
+  }
+
+  /// The address the `index`th (1-based) argument of the current instruction writes to.
+  fn destination(&self, arg: &OpArg, index: usize) -> Result<usize, SymbolicError> {
+    let word = self.get(self.instruction_pointer + index);
+    let destination = match arg {
+      OpArg::Relative(_) => self.relative(word)?,
+      _ => word,
+    };
+    match destination {
+      Expr::Const(address) => Ok(ComputerState::address(&address)?),
+      destination => Err(SymbolicError::SymbolicWrite {
+        address: self.instruction_pointer,
+        destination,
+      }),
+    }
+  }
+
+  fn decode(&self) -> Result<Option<OpCode>, SymbolicError> {
+    let address = self.instruction_pointer;
+    if address >= self.state.len() {
+      return Ok(None);
+    }
+    if self.symbols.contains_key(&address) {
+      return Err(SymbolicError::SymbolicInstruction { address });
+    }
+    Ok(Some(Instruction::at(&self.state, address).decode()?))
+  }
+
+  /// Runs until the program halts.
+  pub fn run(&mut self) -> Result<(), SymbolicError> {
+    while let Some(op_code) = self.decode()? {
+      if op_code.is_done() {
+        break;
+      }
+      self.execute(&op_code)?;
+    }
+    Ok(())
+  }
+
+  fn execute(&mut self, op_code: &OpCode) -> Result<(), SymbolicError> {
+    let address = self.instruction_pointer;
+    let args = op_code.args();
+    match op_code {
+      OpCode::Add(_) | OpCode::Mul(_) | OpCode::LessThan(_) | OpCode::Equals(_) => {
+        let (a, b) = (self.operand(args[0], 1)?, self.operand(args[1], 2)?);
+        let destination = self.destination(args[2], 3)?;
+        let result = match op_code {
+          OpCode::Add(_) => Expr::add(a, b).ok_or_else(|| self.overflow())?,
+          OpCode::Mul(_) => Expr::mul(a, b).ok_or_else(|| self.overflow())?,
+          OpCode::LessThan(_) => Expr::compare(a, b, |a, b| a < b, Expr::LessThan),
+          _ => Expr::compare(a, b, |a, b| a == b, Expr::Equals),
+        };
+        self.set(destination, result);
+      }
+      OpCode::ReadInput { .. } => {
+        let destination = self.destination(args[0], 1)?;
+        self.set(destination, Expr::Symbol(format!("input{}", self.inputs)));
+        self.inputs += 1;
+      }
+      OpCode::SaveOutput { .. } => {
+        let value = self.operand(args[0], 1)?;
+        self.outputs.push(value);
+      }
+      OpCode::JumpIfNonZero(_) | OpCode::JumpIfZero(_) => {
+        let test = match self.operand(args[0], 1)? {
+          Expr::Const(test) => test,
+          condition => return Err(SymbolicError::SymbolicBranch { address, condition }),
+        };
+        let jump_if_zero = matches!(op_code, OpCode::JumpIfZero(_));
+        if test.is_zero() == jump_if_zero {
+          self.instruction_pointer = match self.operand(args[1], 2)? {
+            Expr::Const(target) => ComputerState::address(&target)?,
+            target => return Err(SymbolicError::SymbolicJump { address, target }),
+          };
+          return Ok(());
+        }
+      }
+      OpCode::RelativeAdjustment(_) => match self.operand(args[0], 1)? {
+        Expr::Const(adjustment) => {
+          self.relative_base = CheckedAdd::checked_add(&self.relative_base, &adjustment)
+            .ok_or_else(|| self.overflow())?;
+        }
+        adjustment => {
+          return Err(SymbolicError::SymbolicRelativeBase {
+            address,
+            adjustment,
+          })
+        }
+      },
+      OpCode::Done => return Ok(()),
+    }
+
+    self.instruction_pointer += op_code.size();
+    Ok(())
+  }
+}
+
+/// Runs `state` with each `(address, name)` in `symbols` made a symbol, and
+/// returns the closed form left at `address`.
+pub fn eval_at(
+  state: ComputerState,
+  symbols: &[(usize, &str)],
+  address: usize,
+) -> Result<Expr, SymbolicError> {
+  let mut symbolic = symbols
+    .iter()
+    .fold(Symbolic::new(state), |symbolic, (address, name)| {
+      symbolic.with_symbol(*address, name)
+    });
+  symbolic.run()?;
+  Ok(symbolic.get(address))
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::parse_op_stack;
+
+  fn state(program: &str) -> ComputerState {
+    ComputerState::from(parse_op_stack(program).unwrap())
+  }
+
+  #[test]
+  fn test_straight_line_arithmetic() {
+    // mem[19] = (x + 3) * 5, then mem[0] = mem[19] + y
+    let program = state("1001,20,3,19,1002,19,5,19,1,19,21,0,99");
+    let expr = eval_at(program, &[(20, "x"), (21, "y")], 0).unwrap();
+    assert_eq!(expr.to_string(), "(x + 3) * 5 + y");
+
+    let linear = expr.linear().unwrap();
+    assert_eq!(linear.to_string(), "5 * x + y + 15");
+    assert_eq!(
+      linear.solve(&52, &[("x", 0..=9), ("y", 0..=9)]),
+      Ok(Some(vec![6, 7]))
+    );
+    assert_eq!(linear.solve(&100, &[("x", 0..=9), ("y", 0..=9)]), Ok(None));
+    assert_eq!(
+      linear.solve(&52, &[("x", 0..=9)]),
+      Err(SymbolicError::Unbounded("y".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_non_linear() {
+    // mem[0] = x * y
+    let expr = eval_at(state("2,5,6,0,99,0,0"), &[(5, "x"), (6, "y")], 0).unwrap();
+    assert_eq!(expr.to_string(), "x * y");
+    assert_eq!(expr.linear(), None);
+
+    // mem[0] = mem[x]
+    let expr = eval_at(state("1,0,7,0,99,0,0,0"), &[(1, "x")], 0).unwrap();
+    assert_eq!(expr.to_string(), "mem[x]");
+    assert_eq!(expr.linear(), None);
+  }
+
+  #[test]
+  fn test_linear_simplifies() {
+    let x = || Expr::symbol("x");
+    let expr = Expr::add(
+      Expr::mul(x(), Expr::Const(-2)).unwrap(),
+      Expr::add(x(), Expr::Const(-4)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(expr.linear().unwrap().to_string(), "-x - 4");
+    let cancelled = Expr::add(expr, x()).unwrap().linear().unwrap();
+    assert!(cancelled.terms.is_empty());
+    assert_eq!(cancelled.to_string(), "-4");
+  }
+
+  #[test]
+  fn test_inputs_become_symbols() {
+    let mut symbolic = Symbolic::new(state("3,9,1001,9,1,10,4,10,99"));
+    symbolic.run().unwrap();
+    assert_eq!(symbolic.outputs()[0].to_string(), "input0 + 1");
+  }
+
+  #[test]
+  fn test_symbolic_control_flow() {
+    assert_eq!(
+      eval_at(state("1005,3,0,0,99"), &[(3, "x")], 0),
+      Err(SymbolicError::SymbolicBranch {
+        address: 0,
+        condition: Expr::symbol("x")
+      })
+    );
+    assert_eq!(
+      eval_at(state("1,0,0,0,99"), &[(3, "x")], 0),
+      Err(SymbolicError::SymbolicWrite {
+        address: 0,
+        destination: Expr::symbol("x")
+      })
+    );
+    assert_eq!(
+      eval_at(state("1101,1,1,4,0"), &[(0, "x")], 0),
+      Err(SymbolicError::SymbolicInstruction { address: 0 })
+    );
+    assert_eq!(
+      eval_at(state("1101,40,2,4,0"), &[], 0)
+        .unwrap_err()
+        .to_string(),
+      "Invalid opcode: 42 @4"
+    );
+  }
+}
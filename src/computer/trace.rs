@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::{Computer, ComputerError, ComputerWord, OpCode};
+
+/// A single word read from or written to memory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Access {
+  pub address: usize,
+  pub value: ComputerWord,
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceEntry {
+  pub step: usize,
+  pub address: usize,
+  pub op_code: OpCode,
+  pub relative_base: ComputerWord,
+  /// The resolved value of every argument the instruction reads, in order.
+  pub operands: Vec<ComputerWord>,
+  pub reads: Vec<Access>,
+  pub writes: Vec<Access>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Watch {
+  Read,
+  Write,
+  ReadWrite,
+}
+
+impl Watch {
+  fn covers(self, kind: Watch) -> bool {
+    self == Watch::ReadWrite || self == kind
+  }
+}
+
+/// A watched address being touched. `kind` is either [`Watch::Read`] or [`Watch::Write`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WatchHit {
+  pub step: usize,
+  /// The address of the instruction that touched the watched address.
+  pub instruction: usize,
+  pub kind: Watch,
+  pub access: Access,
+}
+
+/// Execution trace and watchpoints for a [`Computer`], see [`Computer::with_trace`].
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+  record: bool,
+  steps: usize,
+  entries: Vec<TraceEntry>,
+  watchpoints: BTreeMap<usize, Watch>,
+  hits: Vec<WatchHit>,
+}
+
+impl Trace {
+  /// Records every executed instruction.
+  pub fn new() -> Self {
+    Trace {
+      record: true,
+      ..Trace::default()
+    }
+  }
+
+  /// Only checks watchpoints, without keeping a record of each instruction.
+  pub fn watch_only() -> Self {
+    Trace::default()
+  }
+
+  pub fn watch(&mut self, address: usize, kind: Watch) {
+    self.watchpoints.insert(address, kind);
+  }
+
+  pub fn unwatch(&mut self, address: usize) -> bool {
+    self.watchpoints.remove(&address).is_some()
+  }
+
+  pub fn entries(&self) -> &[TraceEntry] {
+    &self.entries
+  }
+
+  pub fn hits(&self) -> &[WatchHit] {
+    &self.hits
+  }
+
+  /// How many instructions have executed while tracing.
+  pub fn steps(&self) -> usize {
+    self.steps
+  }
+
+  /// Writes one JSON object per executed instruction.
+  pub fn write_json_lines(&self, mut out: impl Write) -> io::Result<()> {
+    for entry in &self.entries {
+      writeln!(out, "{}", entry.to_json())?;
+    }
+    Ok(())
+  }
+
+  fn check_watchpoints(&mut self, entry: &TraceEntry) {
+    let accesses = entry
+      .reads
+      .iter()
+      .map(|access| (Watch::Read, access))
+      .chain(entry.writes.iter().map(|access| (Watch::Write, access)));
+
+    for (kind, access) in accesses {
+      if let Some(watch) = self.watchpoints.get(&access.address) {
+        if watch.covers(kind) {
+          self.hits.push(WatchHit {
+            step: entry.step,
+            instruction: entry.address,
+            kind,
+            access: access.clone(),
+          });
+        }
+      }
+    }
+  }
+
+  fn record(&mut self, entry: TraceEntry) {
+    self.steps += 1;
+    self.check_watchpoints(&entry);
+    if self.record {
+      self.entries.push(entry);
+    }
+  }
+}
+
+fn json_accesses(accesses: &[Access]) -> String {
+  let accesses: Vec<String> = accesses
+    .iter()
+    .map(|access| {
+      format!(
+        "{{\"address\":{},\"value\":{}}}",
+        access.address, access.value
+      )
+    })
+    .collect();
+  format!("[{}]", accesses.join(","))
+}
+
+impl TraceEntry {
+  pub fn to_json(&self) -> String {
+    let operands: Vec<String> = self.operands.iter().map(|o| o.to_string()).collect();
+    // Op codes only ever display as mnemonics, numbers and `@`, `r`, `,`, `->`,
+    // so there's nothing in them that needs escaping.
+    format!(
+      "{{\"step\":{},\"address\":{},\"op\":\"{}\",\"relative_base\":{},\"operands\":[{}],\"reads\":{},\"writes\":{}}}",
+      self.step,
+      self.address,
+      self.op_code,
+      self.relative_base,
+      operands.join(","),
+      json_accesses(&self.reads),
+      json_accesses(&self.writes)
+    )
+  }
+}
+
+/// What an instruction is about to read and where it will write, captured
+/// before it runs so the writes can be filled in afterwards.
+pub(super) struct PendingEntry {
+  entry: TraceEntry,
+  destination: Option<usize>,
+}
+
+impl PendingEntry {
+  pub(super) fn before(computer: &Computer, op_code: &OpCode) -> Result<Self, ComputerError> {
+    let mut operands = Vec::new();
+    let mut reads = Vec::new();
+    for arg in op_code.sources() {
+      let value = arg.resolve(computer)?;
+      if let Some(address) = computer.address_of(arg)? {
+        reads.push(Access {
+          address,
+          value: value.clone(),
+        });
+      }
+      operands.push(value);
+    }
+
+    let destination = match op_code.destination() {
+      Some(arg) => computer.address_of(arg)?,
+      None => None,
+    };
+
+    let steps = computer.trace.as_ref().map_or(0, |trace| trace.steps);
+    Ok(PendingEntry {
+      entry: TraceEntry {
+        step: steps,
+        address: computer.instruction_pointer,
+        op_code: op_code.clone(),
+        relative_base: computer.relative_base.clone(),
+        operands,
+        reads,
+        writes: vec![],
+      },
+      destination,
+    })
+  }
+
+  pub(super) fn finish(self, computer: &mut Computer) {
+    let mut entry = self.entry;
+    if let Some(address) = self.destination {
+      entry.writes.push(Access {
+        address,
+        value: computer.resolve(address),
+      });
+    }
+    if let Some(trace) = computer.trace.as_mut() {
+      trace.record(entry);
+    }
+  }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
+mod tests {
+  use super::*;
+  use crate::computer::from;
+
+  #[test]
+  fn test_records_instructions() {
+    let mut computer = from("1002,4,3,4,33").unwrap().with_trace(Trace::new());
+    computer.eval().unwrap();
+
+    let trace = computer.take_trace().unwrap();
+    let entries = trace.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].operands, vec![33, 3]);
+    assert_eq!(
+      entries[0].reads,
+      vec![Access {
+        address: 4,
+        value: 33
+      }]
+    );
+    assert_eq!(
+      entries[0].writes,
+      vec![Access {
+        address: 4,
+        value: 99
+      }]
+    );
+    assert_eq!(entries[1].op_code, OpCode::Done);
+  }
+
+  #[test]
+  fn test_json_lines() {
+    let mut computer = from("109,10,203,1,204,1,99")
+      .unwrap()
+      .add_input(7)
+      .with_trace(Trace::new());
+    computer.eval().unwrap();
+
+    let mut out = Vec::new();
+    computer
+      .take_trace()
+      .unwrap()
+      .write_json_lines(&mut out)
+      .unwrap();
+    let lines: Vec<String> = String::from_utf8(out)
+      .unwrap()
+      .lines()
+      .map(String::from)
+      .collect();
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+      lines[1],
+      "{\"step\":1,\"address\":2,\"op\":\"in -> r1\",\"relative_base\":10,\"operands\":[],\"reads\":[],\"writes\":[{\"address\":11,\"value\":7}]}"
+    );
+  }
+
+  #[test]
+  fn test_watchpoints() {
+    let mut trace = Trace::watch_only();
+    trace.watch(13, Watch::Write);
+    trace.watch(14, Watch::ReadWrite);
+
+    // Adds its input to @14 twice, then outputs @14.
+    let mut computer = from("3,13,1,13,14,14,1,13,14,14,4,14,99,0,0")
+      .unwrap()
+      .add_input(5)
+      .with_trace(trace);
+    assert_eq!(computer.resume(), Ok(crate::computer::Status::Output(10)));
+
+    let trace = computer.take_trace().unwrap();
+    assert!(trace.entries().is_empty());
+    let hits: Vec<(usize, Watch, usize)> = trace
+      .hits()
+      .iter()
+      .map(|hit| (hit.instruction, hit.kind, hit.access.address))
+      .collect();
+    assert_eq!(
+      hits,
+      vec![
+        (0, Watch::Write, 13),
+        (2, Watch::Read, 14),
+        (2, Watch::Write, 14),
+        (6, Watch::Read, 14),
+        (6, Watch::Write, 14),
+        (10, Watch::Read, 14),
+      ]
+    );
+  }
+}
@@ -1,26 +1,11 @@
-use std::str::FromStr;
+use super::{Solution, SolveError};
 
-fn module_masses() -> impl Iterator<Item = i64> {
-    let modules_text = include_str!("resources/day01.txt");
-
-    modules_text
-        .lines()
-        .map(i64::from_str)
-        .filter(|r| r.is_ok())
-        .map(|r| r.unwrap())
-}
+pub struct Day01;
 
 fn fuel_required(mass: i64) -> i64 {
     mass / 3 - 2
 }
 
-fn part_one() -> i64 {
-    let sum = module_masses().map(fuel_required).sum();
-
-    println!("Fuel for modules: {}", sum);
-    return sum;
-}
-
 fn fuel(mass: i64) -> i64 {
     let fuel = fuel_required(mass);
     let mut total_fuel = fuel;
@@ -29,20 +14,48 @@ fn fuel(mass: i64) -> i64 {
         total_fuel += fuel_fuel;
         fuel_fuel = fuel_required(fuel_fuel)
     }
-    return total_fuel;
+    total_fuel
 }
 
-fn part_two() -> i64 {
-    let sum = module_masses().map(fuel).sum();
+impl Solution for Day01 {
+    const DAY: u32 = 1;
+    const INPUT: &'static str = include_str!("resources/day01.txt");
+
+    /// Module masses.
+    type Parsed = Vec<i64>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(input: &str) -> Result<Vec<i64>, SolveError> {
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.parse()
+                    .map_err(|_| format!("Invalid module mass '{}'", line).into())
+            })
+            .collect()
+    }
+
+    fn part1(masses: &Vec<i64>) -> Result<i64, SolveError> {
+        Ok(masses.iter().copied().map(fuel_required).sum())
+    }
 
-    println!("Fuel for fuel + modules: {}", sum);
-    return sum;
+    fn part2(masses: &Vec<i64>) -> Result<i64, SolveError> {
+        Ok(masses.iter().copied().map(fuel).sum())
+    }
 }
 
-pub fn solve() {
-    let total_mass: i64 = module_masses().sum();
-    println!("Total Mass   : {}", total_mass);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    part_one();
-    part_two();
+    #[test]
+    fn test_examples() {
+        let masses = Day01::parse("12\n14\n1969\n100756\n").unwrap();
+        assert_eq!(Day01::part1(&masses).unwrap(), 2 + 2 + 654 + 33583);
+        assert_eq!(Day01::part2(&masses).unwrap(), 2 + 2 + 966 + 50346);
+        assert!(Day01::parse("12\nabc").is_err());
+    }
 }
@@ -1,40 +1,71 @@
-use crate::computer;
-use computer::{ComputerState, ComputerWord};
+use super::{Solution, SolveError};
+use crate::computer::search::PatchSearch;
+use crate::computer::{self, symbolic, ComputerState, ComputerWord};
 
-fn operation_stack(param1: i32, param2: i32) -> ComputerState {
-    let modules_text = include_str!("resources/day02.txt");
-    let mut operations = computer::parse_op_stack(modules_text);
+pub struct Day02;
 
-    operations[1] = ComputerWord::from(param1);
-    operations[2] = ComputerWord::from(param2);
+fn run_with(program: &[ComputerWord], noun: i32, verb: i32) -> Result<ComputerWord, SolveError> {
+    let mut operations = program.to_vec();
+    if operations.len() < 3 {
+        return Err("The program needs room for a noun and a verb".into());
+    }
+    operations[1] = ComputerWord::from(noun);
+    operations[2] = ComputerWord::from(verb);
+
+    Ok(computer::Computer::new(operations).eval()?)
+}
+
+/// The first noun and verb, in order, that leave `solution` at address 0.
+fn noun_and_verb(
+    program: &[ComputerWord],
+    solution: &ComputerWord,
+) -> Result<Option<Vec<ComputerWord>>, SolveError> {
+    let state = ComputerState::from(program.to_vec());
+
+    // Usually the program is straight-line arithmetic over the noun and verb,
+    // which can be solved directly; otherwise try every pair.
+    let closed_form = symbolic::eval_at(state.clone(), &[(1, "noun"), (2, "verb")], 0);
+    if let Some(linear) = closed_form.ok().and_then(|expr| expr.linear()) {
+        return Ok(linear.solve(solution, &[("noun", 0..=99), ("verb", 0..=99)])?);
+    }
 
-    return operations;
+    let matches = PatchSearch::new(state)
+        .vary(1, 0..=99)
+        .vary(2, 0..=99)
+        .run(|computer| &computer.resolve(0) == solution)?;
+    Ok(matches.into_iter().next())
 }
 
-fn solve_for(solution: i32) {
+fn solve_for(program: &[ComputerWord], solution: i32) -> Result<ComputerWord, SolveError> {
     let solution = ComputerWord::from(solution);
-    let stack = operation_stack(0, 0);
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut stack = stack.clone();
-            stack[1] = ComputerWord::from(noun);
-            stack[2] = ComputerWord::from(verb);
-            let mut computer = computer::Computer::new(stack);
-            let result = computer.eval();
-            if result == solution {
-                println!("Solved: {}", 100 * noun + verb);
-                return;
-            }
+    match noun_and_verb(program, &solution)? {
+        Some(assignment) => {
+            let (noun, verb) = (&assignment[0], &assignment[1]);
+            Ok(ComputerWord::from(100) * noun + verb)
         }
+        None => Err(format!("No noun and verb produce {}", solution).into()),
     }
-    panic!("Can't solve");
 }
 
-pub fn solve() {
-    let stack = operation_stack(12, 2);
-    let mut computer = computer::Computer::new(stack);
-    println!("Part 1: {}", computer.eval());
-    solve_for(19690720);
+impl Solution for Day02 {
+    const DAY: u32 = 2;
+    const INPUT: &'static str = include_str!("resources/day02.txt");
+
+    type Parsed = Vec<ComputerWord>;
+    type Part1 = ComputerWord;
+    type Part2 = ComputerWord;
+
+    fn parse(input: &str) -> Result<Vec<ComputerWord>, SolveError> {
+        Ok(computer::parse_op_stack(input)?)
+    }
+
+    fn part1(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        run_with(program, 12, 2)
+    }
+
+    fn part2(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        solve_for(program, 19690720)
+    }
 }
 
 #[cfg(test)]
@@ -44,12 +75,31 @@ mod tests {
     #[test]
     fn test_examples() {
         assert_eq!(
-            computer::from("2,4,4,0,99,0").eval(),
-            ComputerWord::from(9801)
+            computer::from("2,4,4,0,99,0").unwrap().eval(),
+            Ok(ComputerWord::from(9801))
         );
         assert_eq!(
-            computer::from("1,1,1,4,99,5,6,0,99").eval(),
-            ComputerWord::from(30)
+            computer::from("1,1,1,4,99,5,6,0,99").unwrap().eval(),
+            Ok(ComputerWord::from(30))
         );
     }
+
+    #[test]
+    fn test_solve_for() {
+        let program = Day02::parse("1,0,0,0,99").unwrap();
+        assert_eq!(solve_for(&program, 198).unwrap(), ComputerWord::from(404));
+        assert!(solve_for(&program, -1).is_err());
+
+        // Leaves noun + verb at address 0, which is solved without a search.
+        let program = Day02::parse("1,0,0,0,1,1,2,0,99").unwrap();
+        assert_eq!(solve_for(&program, 150).unwrap(), ComputerWord::from(5199));
+        assert!(solve_for(&program, 199).is_err());
+    }
+
+    #[test]
+    fn test_input_is_linear() {
+        let state = ComputerState::from(Day02::parse(Day02::INPUT).unwrap());
+        let closed_form = symbolic::eval_at(state, &[(1, "noun"), (2, "verb")], 0).unwrap();
+        assert!(closed_form.linear().is_some(), "{}", closed_form);
+    }
 }
@@ -1,3 +1,4 @@
+use super::{Solution, SolveError};
 use crate::coordinates::Point;
 use std::collections::HashSet;
 use std::ops::Range;
@@ -181,10 +182,16 @@ fn parse_wire_path(input: &str) -> WirePath {
     .segments
 }
 
-fn parse_paths(input: &str) -> (WirePath, WirePath) {
-  let lines: Vec<WirePath> = input.lines().map(parse_wire_path).collect();
-  assert_eq!(lines.len(), 2);
-  (lines[0].clone(), lines[1].clone())
+fn parse_paths(input: &str) -> Result<(WirePath, WirePath), SolveError> {
+  let lines: Vec<WirePath> = input
+    .lines()
+    .filter(|line| !line.trim().is_empty())
+    .map(parse_wire_path)
+    .collect();
+  if lines.len() != 2 {
+    return Err(format!("Expected 2 wire paths, found {}", lines.len()).into());
+  }
+  Ok((lines[0].clone(), lines[1].clone()))
 }
 
 pub fn all_points(path: &WirePath) -> HashSet<Point> {
@@ -213,30 +220,35 @@ pub fn find_closest(
   points.first().cloned()
 }
 
-fn part1(input: &str) {
-  let (first, second) = parse_paths(input);
+pub struct Day03;
 
-  let result = find_closest((&first, &second), distances::manhattan).expect("No closest Point");
-  println!("Distance: {}", distances::manhattan(&result));
-}
+impl Solution for Day03 {
+  const DAY: u32 = 3;
+  const INPUT: &'static str = include_str!("resources/day03.txt");
 
-fn part2(input: &str) {
-  let (first, second) = parse_paths(input);
-  let distance_to = distances::wire_paths(&first, &second);
+  type Parsed = (WirePath, WirePath);
+  type Part1 = i32;
+  type Part2 = i32;
 
-  let result = find_closest((&first, &second), distance_to).expect("No closest Point");
+  fn parse(input: &str) -> Result<(WirePath, WirePath), SolveError> {
+    parse_paths(input)
+  }
 
-  let distance_to = distances::wire_paths(&first, &second);
+  fn part1((first, second): &(WirePath, WirePath)) -> Result<i32, SolveError> {
+    let result =
+      find_closest((first, second), distances::manhattan).ok_or("The wires never cross")?;
+    Ok(distances::manhattan(&result))
+  }
 
-  println!("Distance: {}", distance_to(&result));
-}
+  fn part2((first, second): &(WirePath, WirePath)) -> Result<i32, SolveError> {
+    let distance_to = distances::wire_paths(first, second);
 
-pub fn solve() {
-  let paths_text = include_str!("resources/day03.txt");
-  part1(paths_text);
-  part2(paths_text)
-  // let (first, second) = parse_paths(paths_text);
-  // to_svg::make_svg(first, second);
+    let result = find_closest((first, second), distance_to).ok_or("The wires never cross")?;
+
+    let distance_to = distances::wire_paths(first, second);
+
+    Ok(distance_to(&result))
+  }
 }
 
 #[cfg(test)]
@@ -278,7 +290,7 @@ mod tests {
     let input = "R75,D30,R83,U83,L12,D49,R71,U7,L72
 U62,R66,U55,R34,D71,R55,D58,R83";
 
-    let (first, second) = parse_paths(input);
+    let (first, second) = parse_paths(input).unwrap();
 
     assert_eq!(
       distances::manhattan(&find_closest((&first, &second), distances::manhattan).unwrap()),
@@ -288,11 +300,12 @@ U62,R66,U55,R34,D71,R55,D58,R83";
 
     let input = "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
 U98,R91,D20,R16,D67,R40,U7,R15,U6,R7";
-    let (first, second) = parse_paths(input);
+    let (first, second) = parse_paths(input).unwrap();
 
     assert_eq!(
       distances::manhattan(&find_closest((&first, &second), distances::manhattan).unwrap()),
       135
-    )
+    );
+    assert_eq!(Day03::part2(&Day03::parse(input).unwrap()).unwrap(), 410);
   }
 }
@@ -1,6 +1,9 @@
+use super::{Solution, SolveError};
 use std::collections::HashMap;
 use std::ops::RangeInclusive;
 
+pub struct Day04;
+
 fn get_digits(mut num: i32, buffer: &mut Vec<i32>) {
     buffer.clear();
     while num > 0 {
@@ -80,15 +83,42 @@ fn part2(range: RangeInclusive<i32>) -> usize {
         .count()
 }
 
-pub fn solve() {
-    println!("Digits Between: {}", part1(138241..=674034));
-    println!("Digits Part 02: {}", part2(138241..=674034));
+impl Solution for Day04 {
+    const DAY: u32 = 4;
+    const INPUT: &'static str = "138241-674034";
+
+    type Parsed = RangeInclusive<i32>;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<RangeInclusive<i32>, SolveError> {
+        let mut bounds = input.trim().splitn(2, '-').map(str::parse::<i32>);
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(start)), Some(Ok(end))) => Ok(start..=end),
+            _ => Err(format!("Expected a range like 100-200, got '{}'", input.trim()).into()),
+        }
+    }
+
+    fn part1(range: &RangeInclusive<i32>) -> Result<usize, SolveError> {
+        Ok(part1(range.clone()))
+    }
+
+    fn part2(range: &RangeInclusive<i32>) -> Result<usize, SolveError> {
+        Ok(part2(range.clone()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse() {
+        assert_eq!(Day04::parse("100-200\n").unwrap(), 100..=200);
+        assert!(Day04::parse("100").is_err());
+        assert!(Day04::parse("a-b").is_err());
+    }
+
     #[test]
     fn test_digits_fun() {
         let mut buffer = Vec::new();
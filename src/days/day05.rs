@@ -1,44 +1,60 @@
-use crate::computer;
-
-fn part1() {
-    let day = include_str!("resources/day05.txt");
-    let comp = computer::from(day).add_input(1);
-    let outputs = comp.run();
-    for (idx, output) in outputs.iter().enumerate() {
-        println!("[{}] {}", idx, output)
+use num::Zero;
+
+use super::{Solution, SolveError};
+use crate::computer::{self, Computer, ComputerWord};
+
+pub struct Day05;
+
+/// Runs the diagnostic program for `system_id`, checking every test before
+/// the final diagnostic code came back as zero.
+fn diagnostic_code(program: &[ComputerWord], system_id: i64) -> Result<ComputerWord, SolveError> {
+    let mut outputs = Computer::new(program.to_vec()).add_input(system_id).run()?;
+    let code = outputs
+        .pop()
+        .ok_or("The diagnostic program produced no output")?;
+    match outputs.iter().position(|output| !output.is_zero()) {
+        Some(test) => Err(format!("Diagnostic test {} failed with {}", test, outputs[test]).into()),
+        None => Ok(code),
     }
 }
 
-fn part2() {
-    let day = include_str!("resources/day05.txt");
-    let comp = computer::from(day).add_input(5);
-    println!("States: {}", comp.len());
-    let outputs = comp.run();
-    for (idx, output) in outputs.iter().enumerate() {
-        println!("[{}] {}", idx, output)
+impl Solution for Day05 {
+    const DAY: u32 = 5;
+    const INPUT: &'static str = include_str!("resources/day05.txt");
+
+    type Parsed = Vec<ComputerWord>;
+    type Part1 = ComputerWord;
+    type Part2 = ComputerWord;
+
+    fn parse(input: &str) -> Result<Vec<ComputerWord>, SolveError> {
+        Ok(computer::parse_op_stack(input)?)
     }
-}
 
-pub fn solve() {
-    part1();
-    part2();
+    fn part1(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        diagnostic_code(program, 1)
+    }
+
+    fn part2(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        diagnostic_code(program, 5)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "bigint")))]
 mod tests {
-    use crate::computer::{self, from, ComputerWord};
+    use super::*;
+    use crate::computer::from;
     use num::ToPrimitive;
 
     #[test]
     fn test_part1_rules() {
-        let mut computer = from("1002,4,3,4,33");
-        assert_eq!(computer.eval_at(4).clone(), 99.into());
+        let mut computer = from("1002,4,3,4,33").unwrap();
+        assert_eq!(computer.eval_at(4), Ok(99));
     }
 
     #[test]
     fn test_part2() {
         fn single_input(program: &str, input: i64, expected: i32) {
-            let single_output = computer::run_single_input(program, input);
+            let single_output = crate::days::run_single_input(program, input);
             assert_eq!(
                 single_output,
                 ComputerWord::from(expected),
@@ -74,9 +90,21 @@ mod tests {
     #[test]
     fn test_part2_rules() {
         let input = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
-        let run = |i: i64| computer::run_single_input(input, i).to_i32().unwrap();
+        let run = |i: i64| crate::days::run_single_input(input, i).to_i32().unwrap();
         assert_eq!(run(5), 999);
         assert_eq!(run(8), 1000);
         assert_eq!(run(9), 1001);
     }
+
+    #[test]
+    fn test_diagnostic_code() {
+        let program = computer::parse_op_stack("104,0,104,0,4,7,99,42").unwrap();
+        assert_eq!(diagnostic_code(&program, 1).unwrap(), 42);
+
+        let program = computer::parse_op_stack("104,0,104,3,4,7,99,42").unwrap();
+        assert_eq!(
+            diagnostic_code(&program, 1).unwrap_err().to_string(),
+            "Diagnostic test 1 failed with 3"
+        );
+    }
 }
@@ -2,20 +2,26 @@ use std::collections::hash_map::OccupiedEntry;
 use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
 
+use super::{Solution, SolveError};
+
 #[derive(Debug)]
-struct Edge {
+pub struct Edge {
   center: String,
   moon: String,
 }
 
-fn parse_orbits(orbits_text: &str) -> Vec<Edge> {
+fn parse_orbits(orbits_text: &str) -> Result<Vec<Edge>, SolveError> {
   orbits_text
     .lines()
+    .filter(|l| !l.trim().is_empty())
     .map(|l| {
-      let mut split = l.splitn(2, ')');
-      Edge {
-        center: split.next().unwrap().to_string(),
-        moon: split.next().unwrap().to_string(),
+      let mut split = l.trim().splitn(2, ')');
+      match (split.next(), split.next()) {
+        (Some(center), Some(moon)) => Ok(Edge {
+          center: center.to_string(),
+          moon: moon.to_string(),
+        }),
+        _ => Err(format!("Invalid orbit '{}'", l).into()),
       }
     })
     .collect()
@@ -74,21 +80,27 @@ fn transfer_count(orbits: &[Edge], to: &str, from: &str) -> usize {
   unreachable!("No path between Nodes")
 }
 
-fn part1(orbits: &[Edge]) {
-  let all_orbits = count_orbits(orbits);
-  println!("Orbits: {}", all_orbits);
-}
+pub struct Day06;
 
-fn part2(orbits: &[Edge]) {
-  let distance = transfer_count(orbits, "SAN", "YOU");
+impl Solution for Day06 {
+  const DAY: u32 = 6;
+  const INPUT: &'static str = include_str!("resources/day06.txt");
 
-  println!("Distance: {}", distance);
-}
+  type Parsed = Vec<Edge>;
+  type Part1 = usize;
+  type Part2 = usize;
+
+  fn parse(input: &str) -> Result<Vec<Edge>, SolveError> {
+    parse_orbits(input)
+  }
+
+  fn part1(orbits: &Vec<Edge>) -> Result<usize, SolveError> {
+    Ok(count_orbits(orbits))
+  }
 
-pub fn solve() {
-  let orbits = parse_orbits(include_str!("resources/day06.txt"));
-  part1(&orbits);
-  part2(&orbits);
+  fn part2(orbits: &Vec<Edge>) -> Result<usize, SolveError> {
+    Ok(transfer_count(orbits, "SAN", "YOU"))
+  }
 }
 
 #[cfg(test)]
@@ -109,7 +121,7 @@ E)J
 J)K
 K)L";
 
-    let orbits = parse_orbits(orbits);
+    let orbits = parse_orbits(orbits).unwrap();
     assert_eq!(count_orbits(&orbits), 42)
   }
 
@@ -129,7 +141,7 @@ K)L
 K)YOU
 I)SAN";
 
-    let orbits = parse_orbits(orbits);
+    let orbits = parse_orbits(orbits).unwrap();
     let distance = transfer_count(&orbits, "YOU", "SAN");
     println!("{:?}", distance);
     assert_eq!(distance, 4);
@@ -1,17 +1,105 @@
-fn part1() {}
+use super::{Solution, SolveError};
+use crate::computer::{self, pipeline, ComputerWord};
 
-fn part2() {}
+/// No puzzle input was checked in for day 07; pass one with `--input`.
+pub struct Day07;
 
-pub fn solve() {
-    part1();
-    part2();
+fn max_signal(
+    program: &[ComputerWord],
+    settings: std::ops::Range<i64>,
+    feedback: bool,
+) -> Result<ComputerWord, SolveError> {
+    pipeline::max_signal(program, &computer::words(settings), feedback)?
+        .map(|(signal, _)| signal)
+        .ok_or_else(|| "The amplifiers never produced a signal".into())
 }
 
-#[cfg(test)]
+impl Solution for Day07 {
+    const DAY: u32 = 7;
+    // The puzzle input for day 07 was never checked in, so this is empty
+    // rather than a placeholder file; the runner and `verify` skip days
+    // with no input.
+    const INPUT: &'static str = "";
+
+    type Parsed = Vec<ComputerWord>;
+    type Part1 = ComputerWord;
+    type Part2 = ComputerWord;
+
+    fn parse(input: &str) -> Result<Vec<ComputerWord>, SolveError> {
+        Ok(computer::parse_op_stack(input)?)
+    }
+
+    fn part1(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        max_signal(program, 0..5, false)
+    }
+
+    fn part2(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        max_signal(program, 5..10, true)
+    }
+}
+
+#[cfg(all(test, not(feature = "bigint")))]
 mod tests {
+    use super::*;
+
+    fn best(
+        program: &str,
+        phases: &[ComputerWord],
+        feedback: bool,
+    ) -> (ComputerWord, Vec<ComputerWord>) {
+        let program = computer::parse_op_stack(program).unwrap();
+        pipeline::max_signal(&program, phases, feedback)
+            .unwrap()
+            .unwrap()
+    }
+
     #[test]
-    fn test_part1_rules() {}
+    fn test_part1_rules() {
+        let phases = [0, 1, 2, 3, 4];
+        assert_eq!(
+            best(
+                "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+                &phases,
+                false
+            ),
+            (43210, vec![4, 3, 2, 1, 0])
+        );
+        assert_eq!(
+            best(
+                "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+                &phases,
+                false
+            ),
+            (54321, vec![0, 1, 2, 3, 4])
+        );
+        assert_eq!(
+            best(
+                "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0",
+                &phases,
+                false
+            ),
+            (65210, vec![1, 0, 4, 3, 2])
+        );
+    }
 
     #[test]
-    fn test_part2_rules() {}
+    fn test_part2_rules() {
+        let phases = [5, 6, 7, 8, 9];
+        assert_eq!(
+            best(
+                "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+                &phases,
+                true
+            ),
+            (139629729, vec![9, 8, 7, 6, 5])
+        );
+        assert_eq!(
+            best(
+                "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+                &phases,
+                true
+            ),
+            (18216, vec![9, 7, 8, 5, 6])
+        );
+    }
 }
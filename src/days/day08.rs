@@ -1,3 +1,10 @@
+use std::fmt::{Display, Error, Formatter};
+
+use super::{Solution, SolveError};
+
+const WIDTH: usize = 25;
+const HEIGHT: usize = 6;
+
 fn parse_layers(image: Vec<i32>, width: usize, height: usize) -> Vec<Vec<i32>> {
     image
         .chunks(width * height)
@@ -28,9 +35,8 @@ fn merge_image(image_layers: Vec<Vec<i32>>) -> Vec<i32> {
     image_layers.iter().skip(1).fold(base, merge_layers)
 }
 
-fn part1() -> usize {
-    let input = include_str!("resources/day08.txt");
-    let layers = parse_layers(parse(input), 25, 6);
+fn checksum(digits: &[i32]) -> usize {
+    let layers = parse_layers(digits.to_vec(), WIDTH, HEIGHT);
     let layer = layers
         .iter()
         .min_by_key(|l| l.iter().filter(|&&i| i == 0).count())
@@ -42,29 +48,66 @@ fn part1() -> usize {
     ones.len() * twos.len()
 }
 
-fn part2() {
-    let input = include_str!("resources/day08.txt");
-    let mut layers = parse_layers(parse(input), 25, 6);
-    layers.reverse();
-    let image = merge_image(layers);
-    for row in image.chunks(25) {
-        for &v in row {
-            print!(
-                "{}",
-                match v {
-                    0 => ' ',
-                    1 => '*',
-                    _ => unreachable!(),
-                }
-            );
-        }
-        println!();
+/// The decoded image, drawn with `*` for white pixels.
+pub struct Image {
+    pixels: Vec<i32>,
+    width: usize,
+}
+
+impl Display for Image {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let rows: Vec<String> = self
+            .pixels
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&v| match v {
+                        1 => '*',
+                        _ => ' ',
+                    })
+                    .collect()
+            })
+            .collect();
+        write!(f, "{}", rows.join("\n"))
     }
 }
 
-pub fn solve() {
-    println!("Digits Between: {}", part1());
-    part2();
+pub struct Day08;
+
+impl Solution for Day08 {
+    const DAY: u32 = 8;
+    const INPUT: &'static str = include_str!("resources/day08.txt");
+
+    type Parsed = Vec<i32>;
+    type Part1 = usize;
+    type Part2 = Image;
+
+    fn parse(input: &str) -> Result<Vec<i32>, SolveError> {
+        let digits = parse(input);
+        if digits.is_empty() || !digits.len().is_multiple_of(WIDTH * HEIGHT) {
+            return Err(format!(
+                "Expected whole {}x{} layers, got {} pixels",
+                WIDTH,
+                HEIGHT,
+                digits.len()
+            )
+            .into());
+        }
+        Ok(digits)
+    }
+
+    fn part1(digits: &Vec<i32>) -> Result<usize, SolveError> {
+        Ok(checksum(digits))
+    }
+
+    fn part2(digits: &Vec<i32>) -> Result<Image, SolveError> {
+        let mut layers = parse_layers(digits.clone(), WIDTH, HEIGHT);
+        layers.reverse();
+        Ok(Image {
+            pixels: merge_image(layers),
+            width: WIDTH,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -79,4 +122,15 @@ mod tests {
 
     #[test]
     fn test_part2_rules() {}
+
+    #[test]
+    fn test_image() {
+        let mut layers = parse_layers(parse("0222112222120000"), 2, 2);
+        layers.reverse();
+        let image = Image {
+            pixels: merge_image(layers),
+            width: 2,
+        };
+        assert_eq!(image.to_string(), " *\n* ");
+    }
 }
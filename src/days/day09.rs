@@ -1,35 +1,49 @@
-use crate::computer;
+use super::{Solution, SolveError};
+use crate::computer::{self, Computer, ComputerWord};
 
-fn part1() {
-    let day = include_str!("resources/day09.txt");
-    let comp = computer::from(day).add_input(1);
-    let outputs = comp.run();
-    for (idx, output) in outputs.iter().enumerate() {
-        println!("[{}] {}", idx, output);
+pub struct Day09;
+
+/// Runs BOOST in `mode`; anything it outputs besides a single keycode is a
+/// list of opcodes it found to be broken.
+fn boost(program: &[ComputerWord], mode: i64) -> Result<ComputerWord, SolveError> {
+    let mut outputs = Computer::new(program.to_vec()).add_input(mode).run()?;
+    if outputs.len() == 1 {
+        return Ok(outputs.remove(0));
     }
+
+    let outputs: Vec<String> = outputs.iter().map(|o| o.to_string()).collect();
+    Err(format!("BOOST reported malfunctions: [{}]", outputs.join(", ")).into())
 }
 
-fn part2() {
-    let day = include_str!("resources/day09.txt");
-    let comp = computer::from(day).add_input(2);
-    let outputs = comp.run();
-    for (idx, output) in outputs.iter().enumerate() {
-        println!("[{}] {}", idx, output);
+impl Solution for Day09 {
+    const DAY: u32 = 9;
+    const INPUT: &'static str = include_str!("resources/day09.txt");
+
+    type Parsed = Vec<ComputerWord>;
+    type Part1 = ComputerWord;
+    type Part2 = ComputerWord;
+
+    fn parse(input: &str) -> Result<Vec<ComputerWord>, SolveError> {
+        Ok(computer::parse_op_stack(input)?)
+    }
+
+    fn part1(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        boost(program, 1)
     }
-}
 
-pub fn solve() {
-    part1();
-    part2();
+    fn part2(program: &Vec<ComputerWord>) -> Result<ComputerWord, SolveError> {
+        boost(program, 2)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "bigint")))]
 mod tests {
-    use crate::computer;
+    use super::*;
+
     #[test]
     fn test_large_number() {
         assert_eq!(
-            computer::run_single_input("104,1125899906842624,99", 0),
+            crate::days::run_single_input("104,1125899906842624,99", 0),
             1125899906842624
         );
     }
@@ -37,7 +51,7 @@ mod tests {
     #[test]
     fn test_large_number_operations() {
         assert_eq!(
-            computer::run_single_input("1102,34915192,34915192,7,4,7,99,0", 0),
+            crate::days::run_single_input("1102,34915192,34915192,7,4,7,99,0", 0),
             1219070632396864
         );
     }
@@ -45,7 +59,7 @@ mod tests {
     #[test]
     fn test_relative_adjustments() {
         assert_eq!(
-            computer::run_single_input(
+            crate::days::run_single_input(
                 "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
                 0
             ),
@@ -55,4 +69,16 @@ mod tests {
 
     #[test]
     fn test_part2_rules() {}
+
+    #[test]
+    fn test_boost() {
+        let program = computer::parse_op_stack("104,7,99").unwrap();
+        assert_eq!(boost(&program, 1).unwrap(), 7);
+
+        let program = computer::parse_op_stack("104,203,104,7,99").unwrap();
+        assert_eq!(
+            boost(&program, 1).unwrap_err().to_string(),
+            "BOOST reported malfunctions: [203, 7]"
+        );
+    }
 }
@@ -1,8 +1,11 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Error, Formatter};
 
 use crate::coordinates::{Point, Slope};
 
-struct Map {
+use super::{Solution, SolveError, Unsolved};
+
+pub struct Map {
   objects: Vec<Vec<bool>>,
 }
 
@@ -53,12 +56,20 @@ impl Map {
   }
 }
 
-struct BestPoint {
+/// The best place for a monitoring station, shown as how many asteroids it
+/// can see and where it is.
+pub struct BestPoint {
   point: Point,
   count: i32,
   results: HashMap<Point, i32>,
 }
 
+impl Display for BestPoint {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    write!(f, "{} at {}", self.count, self.point)
+  }
+}
+
 fn find_best(map: &Map) -> BestPoint {
   let objects = map.objects();
 
@@ -89,12 +100,6 @@ fn find_best(map: &Map) -> BestPoint {
   }
 }
 
-fn part1() {
-  let map = Map::from(include_str!("resources/day10.txt"));
-  let best_point = find_best(&map);
-  println!("{} - #{}", best_point.point, best_point.count);
-}
-
 fn laser(center: &Point, map: &Map) -> Vec<Point> {
   let mut result: Vec<Slope> = map
     .objects()
@@ -116,11 +121,32 @@ fn laser(center: &Point, map: &Map) -> Vec<Point> {
   return result.iter().map(|slope| center.add(slope)).collect();
 }
 
-fn part2() {}
+pub struct Day10;
+
+impl Solution for Day10 {
+  const DAY: u32 = 10;
+  const INPUT: &'static str = include_str!("resources/day10.txt");
 
-pub fn solve() {
-  part1();
-  part2();
+  type Parsed = Map;
+  /// How many asteroids the best monitoring station can see, and where it is.
+  type Part1 = BestPoint;
+  type Part2 = i32;
+
+  fn parse(input: &str) -> Result<Map, SolveError> {
+    let map = Map::from(input);
+    if map.objects().is_empty() {
+      return Err("There are no asteroids on the map".into());
+    }
+    Ok(map)
+  }
+
+  fn part1(map: &Map) -> Result<BestPoint, SolveError> {
+    Ok(find_best(map))
+  }
+
+  fn part2(_map: &Map) -> Result<i32, SolveError> {
+    Err(Unsolved.into())
+  }
 }
 
 #[cfg(test)]
@@ -1,3 +1,7 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+
 pub mod day01;
 pub mod day02;
 pub mod day03;
@@ -8,3 +12,132 @@ pub mod day07;
 pub mod day08;
 pub mod day09;
 pub mod day10;
+
+/// Why a day couldn't parse its input or find an answer.
+pub type SolveError = Box<dyn Error + Send + Sync>;
+
+/// What a part fails with until it's solved, so the runner can skip it
+/// rather than count it as a failure.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Unsolved;
+
+impl Display for Unsolved {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Not solved yet")
+  }
+}
+
+impl Error for Unsolved {}
+
+/// One day's puzzle.
+///
+/// The input is parsed once and both parts work from the result, returning
+/// their answers as values rather than printing them.
+pub trait Solution {
+  const DAY: u32;
+  /// The puzzle input checked in under `days/resources`.
+  const INPUT: &'static str;
+
+  type Parsed;
+  type Part1: Display;
+  type Part2: Display;
+
+  fn parse(input: &str) -> Result<Self::Parsed, SolveError>;
+  fn part1(parsed: &Self::Parsed) -> Result<Self::Part1, SolveError>;
+  fn part2(parsed: &Self::Parsed) -> Result<Self::Part2, SolveError>;
+}
+
+/// A [`Solution`] with its types erased, so every day can sit in [`REGISTRY`].
+pub trait Puzzle: Sync {
+  fn day(&self) -> u32;
+  fn input(&self) -> &'static str;
+  fn parse(&self, input: &str) -> Result<Box<dyn Parsed>, SolveError>;
+}
+
+/// A day's parsed input, ready to solve either part.
+pub trait Parsed {
+  /// Solves part `1` or `2`, formatting the answer with its `Display` impl.
+  fn solve(&self, part: usize) -> Result<String, SolveError>;
+}
+
+struct Registered<S>(PhantomData<fn() -> S>);
+
+struct ParsedInput<S: Solution>(S::Parsed);
+
+impl<S: Solution> Parsed for ParsedInput<S> {
+  fn solve(&self, part: usize) -> Result<String, SolveError> {
+    match part {
+      1 => S::part1(&self.0).map(|answer| answer.to_string()),
+      2 => S::part2(&self.0).map(|answer| answer.to_string()),
+      part => Err(format!("There is no part {}", part).into()),
+    }
+  }
+}
+
+impl<S: Solution + 'static> Puzzle for Registered<S> {
+  fn day(&self) -> u32 {
+    S::DAY
+  }
+
+  fn input(&self) -> &'static str {
+    S::INPUT
+  }
+
+  fn parse(&self, input: &str) -> Result<Box<dyn Parsed>, SolveError> {
+    Ok(Box::new(ParsedInput::<S>(S::parse(input)?)))
+  }
+}
+
+/// Every solved day, in order.
+pub static REGISTRY: &[&dyn Puzzle] = &[
+  &Registered::<day01::Day01>(PhantomData),
+  &Registered::<day02::Day02>(PhantomData),
+  &Registered::<day03::Day03>(PhantomData),
+  &Registered::<day04::Day04>(PhantomData),
+  &Registered::<day05::Day05>(PhantomData),
+  &Registered::<day06::Day06>(PhantomData),
+  &Registered::<day07::Day07>(PhantomData),
+  &Registered::<day08::Day08>(PhantomData),
+  &Registered::<day09::Day09>(PhantomData),
+  &Registered::<day10::Day10>(PhantomData),
+];
+
+pub fn find(day: u32) -> Option<&'static dyn Puzzle> {
+  REGISTRY.iter().copied().find(|puzzle| puzzle.day() == day)
+}
+
+/// Runs an Intcode program with one input, returning its last output.
+#[cfg(test)]
+pub fn run_single_input(program: &str, input: i64) -> crate::computer::ComputerWord {
+  crate::computer::from(program)
+    .unwrap()
+    .add_input(input)
+    .run()
+    .unwrap()
+    .pop()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_registry_is_in_day_order() {
+    let days: Vec<u32> = REGISTRY.iter().map(|puzzle| puzzle.day()).collect();
+    assert_eq!(days, (1..=10).collect::<Vec<u32>>());
+    assert_eq!(find(4).map(|puzzle| puzzle.day()), Some(4));
+    assert!(find(25).is_none());
+  }
+
+  #[test]
+  fn test_erased_answers() {
+    let parsed = find(1).unwrap().parse("12\n14\n1969").unwrap();
+    assert_eq!(parsed.solve(1).unwrap(), "658");
+    assert_eq!(parsed.solve(2).unwrap(), "970");
+    assert_eq!(
+      parsed.solve(3).unwrap_err().to_string(),
+      "There is no part 3"
+    );
+  }
+}
@@ -0,0 +1,3 @@
+//! The Intcode computer, built as a library so it can be embedded outside
+//! the day solvers; the binary runs it through the `computer` re-export.
+pub mod computer;
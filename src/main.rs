@@ -1,39 +1,334 @@
-mod computer;
+mod cli;
 mod coordinates;
 mod days;
 mod debug;
+mod runner;
+mod verify;
 
+use std::io::{self, Read};
+use std::path::Path;
+use std::{env, fs, process};
+
+use advent_2019::computer;
+use cli::{
+  BenchOptions, Command, Input, IntcodeOptions, ProgramOptions, RunOptions, VerifyOptions,
+};
+use computer::ascii::{self, Ascii};
+use computer::binary;
+use computer::cfg::ControlFlowGraph;
+use computer::debugger::{self, Debugger};
+use computer::loader;
+use computer::profile::Profile;
+use computer::{Computer, ComputerState, ComputerWord, Status};
 pub use days::*;
+use runner::PartResult;
+use verify::{Answers, Check};
 
-fn main() {
-  println!("-------------\n  Day 01\n-------------");
-  day01::solve();
+/// Loads the program at `path`, naming it in any error.
+fn load_program(path: &Path) -> Result<Vec<ComputerWord>, String> {
+  loader::load(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Starts `program` with `inputs` queued.
+fn start(program: Vec<ComputerWord>, inputs: &[ComputerWord]) -> Computer {
+  let mut computer = Computer::new(program);
+  for input in inputs.iter().cloned() {
+    computer.push_input(input);
+  }
+  computer
+}
+
+fn debug(options: &ProgramOptions) -> Result<(), String> {
+  let computer = start(load_program(&options.program)?, &options.inputs);
+  let mut debugger = Debugger::new(computer);
+  let stdin = io::stdin();
+  debugger::repl(&mut debugger, stdin.lock(), io::stdout()).map_err(|e| e.to_string())
+}
+
+fn profile(options: &ProgramOptions) -> Result<(), String> {
+  let program = load_program(&options.program)?;
+  let mut computer = start(program.clone(), &options.inputs).with_profile(Profile::new());
+
+  let mut outputs = Vec::new();
+  loop {
+    match computer.resume().map_err(|e| e.to_string())? {
+      Status::Output(value) => outputs.push(value.to_string()),
+      Status::NeedsInput => return Err("The program needs more input".to_string()),
+      Status::Halted => break,
+    }
+  }
+
+  let program = ComputerState::from(program);
+  let profile = computer.take_profile().expect("Profiling was turned on");
+  print!("{}", profile.annotated_listing(&program));
+  println!("\nOutput: {}", outputs.join(","));
+  print!("{}", profile.summary(&program));
+  println!("Hot spots:");
+  for (address, hits) in profile.hot_spots(5) {
+    println!("  @{:04} {}", address, hits);
+  }
+  Ok(())
+}
+
+fn cfg(path: &Path) -> Result<(), String> {
+  let program = load_program(path)?;
+  let graph = ControlFlowGraph::build(&ComputerState::from(program));
+  for modification in &graph.self_modifications {
+    eprintln!(
+      "warning: @{} writes to @{}, part of the instruction at @{}",
+      modification.address, modification.target, modification.instruction
+    );
+  }
+  print!("{}", graph.to_dot());
+  Ok(())
+}
+
+fn play(path: &Path) -> Result<(), String> {
+  let mut ascii = Ascii::new(Computer::new(load_program(path)?));
+  let stdin = io::stdin();
+  ascii::play(&mut ascii, stdin.lock(), io::stdout()).map_err(|e| e.to_string())
+}
+
+/// Runs a program on its own and prints its output, comma separated.
+fn intcode(options: &IntcodeOptions) -> Result<(), String> {
+  let program = match &options.program {
+    Input::File(path) => load_program(path)?,
+    Input::Stdin => loader::read(io::stdin()).map_err(|e| format!("stdin: {}", e))?,
+    Input::Builtin => unreachable!("intcode always names its program"),
+  };
+  let output = start(program, &options.inputs)
+    .run()
+    .map_err(|e| e.to_string())?;
+  let output: Vec<String> = output.iter().map(ToString::to_string).collect();
+  println!("{}", output.join(","));
+  Ok(())
+}
+
+/// Converts a text program to binary, or a binary one back to text.
+fn convert(from: &Path, to: &Path) -> Result<(), String> {
+  let bytes = fs::read(from).map_err(|e| format!("{}: {}", from.display(), e))?;
+  let converted = if binary::is_binary(&bytes) {
+    binary::to_text(&bytes)
+      .map(String::into_bytes)
+      .map_err(|e| e.to_string())
+  } else {
+    let text = String::from_utf8(bytes).map_err(|e| e.to_string());
+    text.and_then(|text| binary::from_text(&text, true).map_err(|e| e.to_string()))
+  };
+  let converted = converted.map_err(|e| format!("{}: {}", from.display(), e))?;
+  fs::write(to, &converted).map_err(|e| format!("{}: {}", to.display(), e))?;
+  println!("Wrote {} bytes to {}", converted.len(), to.display());
+  Ok(())
+}
 
-  println!("-------------\n  Day 02\n-------------");
+fn read_input(puzzle: &dyn Puzzle, input: &Input) -> Result<String, String> {
+  match input {
+    Input::Builtin => Ok(puzzle.input().to_string()),
+    Input::Stdin => {
+      let mut text = String::new();
+      io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|e| format!("stdin: {}", e))?;
+      Ok(text)
+    }
+    Input::File(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e)),
+  }
+}
+
+/// The day asked for, or every registered day.
+fn select_puzzles(day: Option<u32>) -> Result<Vec<&'static dyn Puzzle>, String> {
+  match day {
+    Some(day) => days::find(day)
+      .map(|puzzle| vec![puzzle])
+      .ok_or_else(|| format!("There's no solution for day {}", day)),
+    None => Ok(REGISTRY.to_vec()),
+  }
+}
+
+fn print_answer(part: usize, answer: &str) {
+  if answer.contains('\n') {
+    println!("Part {}:\n{}", part, answer);
+  } else {
+    println!("Part {}: {}", part, answer);
+  }
+}
 
-  day02::solve();
+/// Reads `puzzle`'s input, failing if there isn't any to run.
+fn day_input(puzzle: &dyn Puzzle, input: &Input) -> Result<String, String> {
+  let input = read_input(puzzle, input)?;
+  if input.trim().is_empty() {
+    return Err(format!(
+      "No input for day {:02}, pass one with --input",
+      puzzle.day()
+    ));
+  }
+  Ok(input)
+}
+
+/// Prints a day's answers, returning how many of its parts failed.
+fn print_day(puzzle: &dyn Puzzle, results: Result<Vec<PartResult>, String>) -> usize {
+  println!("-------------\n  Day {:02}\n-------------", puzzle.day());
+  let results = match results {
+    Ok(results) => results,
+    Err(error) => {
+      eprintln!("Day {:02}: {}", puzzle.day(), error);
+      return 1;
+    }
+  };
 
-  println!("-------------\n  Day 03\n-------------");
-  day03::solve();
+  let mut failures = 0;
+  for result in results {
+    match result.answer {
+      Ok(Some(answer)) => print_answer(result.part, &answer),
+      Ok(None) => println!("Part {}: not solved yet", result.part),
+      Err(error) => {
+        eprintln!(
+          "Day {:02} part {} failed: {}",
+          puzzle.day(),
+          result.part,
+          error
+        );
+        failures += 1;
+      }
+    }
+  }
+  failures
+}
 
-  println!("-------------\n  Day 04\n-------------");
-  day04::solve();
+fn run(options: &RunOptions) -> Result<(), String> {
+  let puzzles = select_puzzles(options.day)?;
 
-  println!("-------------\n  Day 05\n-------------");
-  day05::solve();
+  let mut inputs = Vec::new();
+  for puzzle in puzzles {
+    match day_input(puzzle, &options.input) {
+      Ok(input) => inputs.push((puzzle, input)),
+      // A missing input only counts as a failure when the day was asked for.
+      Err(error) if options.day.is_none() => eprintln!("{}", error),
+      Err(error) => return Err(error),
+    }
+  }
 
-  println!("-------------\n  Day 06\n-------------");
-  day06::solve();
+  let parts = match options.part {
+    Some(part) => vec![part],
+    None => vec![1, 2],
+  };
+  let mut failures = 0;
+  runner::in_parallel(
+    &inputs,
+    options.jobs.unwrap_or_else(runner::default_jobs),
+    |(puzzle, input)| runner::solve(*puzzle, input, &parts),
+    |(puzzle, _), results| failures += print_day(*puzzle, results),
+  );
+
+  match failures {
+    0 => Ok(()),
+    1 => Err("1 part failed".to_string()),
+    n => Err(format!("{} parts failed", n)),
+  }
+}
 
-  println!("-------------\n  Day 07\n-------------");
-  day07::solve();
+fn verify(options: &VerifyOptions) -> Result<(), String> {
+  let answers = match &options.answers {
+    Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+    None => verify::ANSWERS.to_string(),
+  };
+  let answers = Answers::parse(&answers)?;
+  let puzzles = select_puzzles(options.day)?;
 
-  println!("-------------\n  Day 08\n-------------");
-  day08::solve();
+  let (mut passed, mut failed, mut missing, mut skipped) = (0, 0, 0, 0);
+  for puzzle in puzzles {
+    for report in verify::verify(puzzle, &answers) {
+      match report.check {
+        Check::Pass => passed += 1,
+        Check::Fail { .. } => failed += 1,
+        Check::Missing { .. } => missing += 1,
+        Check::NoInput | Check::Unsolved => skipped += 1,
+      }
+      println!("{}", report);
+    }
+  }
 
-  println!("-------------\n  Day 09\n-------------");
-  day09::solve();
+  println!(
+    "\n{} passed, {} failed, {} missing, {} skipped",
+    passed, failed, missing, skipped
+  );
+  match failed {
+    0 => Ok(()),
+    _ => Err("Some answers don't match".to_string()),
+  }
+}
+
+/// Times the Intcode engines on day 9's BOOST program, in sensor boost mode.
+fn bench_intcode(options: &BenchOptions) -> Result<(), String> {
+  let day09 = days::find(9).expect("Day 9 is registered");
+  let program =
+    computer::parse_op_stack(&day_input(day09, &options.input)?).map_err(|e| e.to_string())?;
+  let timings = runner::time_engines(&program, 2, options.runs)?;
+  if options.json {
+    for timing in &timings {
+      println!("{}", timing.to_json());
+    }
+  } else {
+    println!("{}", runner::engine_table(&timings));
+  }
+  Ok(())
+}
+
+fn bench(options: &BenchOptions) -> Result<(), String> {
+  if options.intcode {
+    return bench_intcode(options);
+  }
+  let puzzles = select_puzzles(options.day)?;
+
+  let mut timings = Vec::new();
+  for puzzle in puzzles {
+    let input = match day_input(puzzle, &options.input) {
+      Ok(input) => input,
+      // Days without an input are only an error when asked for by name.
+      Err(_) if options.day.is_none() => continue,
+      Err(error) => return Err(error),
+    };
+    let timing = runner::time(puzzle, &input, &[1, 2], options.runs)
+      .map_err(|error| format!("Day {:02}: {}", puzzle.day(), error))?;
+    if options.json {
+      println!("{}", timing.to_json());
+    }
+    timings.push(timing);
+  }
+
+  if !options.json {
+    println!("{}", runner::timing_table(&timings));
+  }
+  Ok(())
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let result = match cli::parse(&args) {
+    Ok(Command::Run(options)) => run(&options),
+    Ok(Command::List) => {
+      for puzzle in REGISTRY {
+        println!("Day {:02}", puzzle.day());
+      }
+      Ok(())
+    }
+    Ok(Command::Verify(options)) => verify(&options),
+    Ok(Command::Bench(options)) => bench(&options),
+    Ok(Command::Debug(options)) => debug(&options),
+    Ok(Command::Cfg(path)) => cfg(&path),
+    Ok(Command::Profile(options)) => profile(&options),
+    Ok(Command::Play(path)) => play(&path),
+    Ok(Command::Intcode(options)) => intcode(&options),
+    Ok(Command::Convert(from, to)) => convert(&from, &to),
+    Ok(Command::Help) => {
+      println!("{}", cli::USAGE);
+      Ok(())
+    }
+    Err(error) => Err(format!("{}\n\n{}", error, cli::USAGE)),
+  };
 
-  println!("-------------\n  Day 10\n-------------");
-  day10::solve();
+  if let Err(error) = result {
+    eprintln!("{}", error);
+    process::exit(1);
+  }
 }
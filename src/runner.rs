@@ -0,0 +1,552 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::computer::compile::Compiled;
+use crate::computer::{Computer, ComputerError, ComputerWord};
+use crate::days::{Parsed, Puzzle, SolveError, Unsolved};
+
+/// One part's answer, or why there isn't one.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PartResult {
+  pub part: usize,
+  /// `None` for a part that isn't solved yet.
+  pub answer: Result<Option<String>, String>,
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+  match payload.downcast::<String>() {
+    Ok(message) => *message,
+    Err(payload) => match payload.downcast::<&str>() {
+      Ok(message) => message.to_string(),
+      Err(_) => "panicked".to_string(),
+    },
+  }
+}
+
+/// Runs `solver`, turning both its errors and any panic into a message.
+fn catch<T>(solver: impl FnOnce() -> Result<T, SolveError>) -> Result<T, String> {
+  match panic::catch_unwind(AssertUnwindSafe(solver)) {
+    Ok(result) => result.map_err(|error| error.to_string()),
+    Err(payload) => Err(panic_message(payload)),
+  }
+}
+
+/// Solves `part`, with `None` if it fails with [`Unsolved`].
+fn solve_part(parsed: &dyn Parsed, part: usize) -> Result<Option<String>, String> {
+  catch(|| match parsed.solve(part) {
+    Err(error) if error.is::<Unsolved>() => Ok(None),
+    answer => answer.map(Some),
+  })
+}
+
+/// Parses `input` once, then solves each of `parts` from it.
+///
+/// Fails only if the input can't be parsed; a part that fails is reported
+/// in its [`PartResult`] and doesn't stop the others.
+pub fn solve(puzzle: &dyn Puzzle, input: &str, parts: &[usize]) -> Result<Vec<PartResult>, String> {
+  let parsed =
+    catch(|| puzzle.parse(input)).map_err(|error| format!("Invalid input: {}", error))?;
+
+  Ok(
+    parts
+      .iter()
+      .map(|&part| PartResult {
+        part,
+        answer: solve_part(&*parsed, part),
+      })
+      .collect(),
+  )
+}
+
+pub use crate::computer::search::default_jobs;
+
+/// Runs `work` on each of `items` across up to `jobs` threads.
+///
+/// Results are handed to `report` in the order of `items`, each one as soon
+/// as it and everything before it has finished.
+pub fn in_parallel<T: Sync, R: Send>(
+  items: &[T],
+  jobs: usize,
+  work: impl Fn(&T) -> R + Sync,
+  mut report: impl FnMut(&T, R),
+) {
+  let next = AtomicUsize::new(0);
+  let (sender, receiver) = mpsc::channel();
+  thread::scope(|scope| {
+    for _ in 0..jobs.clamp(1, items.len().max(1)) {
+      let sender = sender.clone();
+      let (next, work) = (&next, &work);
+      scope.spawn(move || loop {
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        match items.get(index) {
+          Some(item) => {
+            if sender.send((index, work(item))).is_err() {
+              break;
+            }
+          }
+          None => break,
+        }
+      });
+    }
+    drop(sender);
+
+    let mut finished = BTreeMap::new();
+    let mut reported = 0;
+    for (index, result) in receiver {
+      finished.insert(index, result);
+      while let Some(result) = finished.remove(&reported) {
+        report(&items[reported], result);
+        reported += 1;
+      }
+    }
+  });
+}
+
+/// The spread of repeated measurements of one step.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Stats {
+  pub min: Duration,
+  pub median: Duration,
+  pub max: Duration,
+}
+
+impl Stats {
+  fn of(mut samples: Vec<Duration>) -> Stats {
+    samples.sort();
+    Stats {
+      min: samples[0],
+      median: samples[samples.len() / 2],
+      max: samples[samples.len() - 1],
+    }
+  }
+}
+
+/// How long a day took to parse its input and to solve each part.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Timing {
+  pub day: u32,
+  pub parse: Stats,
+  /// A part that fails is only timed up to its first failure, and one
+  /// that isn't solved yet is left out.
+  pub parts: Vec<(usize, Result<Stats, String>)>,
+}
+
+/// Parses `input` and solves each of `parts` `runs` times over.
+///
+/// Every run parses from scratch, so the part timings never include parsing.
+pub fn time(
+  puzzle: &dyn Puzzle,
+  input: &str,
+  parts: &[usize],
+  runs: usize,
+) -> Result<Timing, String> {
+  assert!(runs > 0, "Timing needs at least one run");
+
+  let mut parse = Vec::with_capacity(runs);
+  // `None` once a part turns out to be unsolved.
+  let mut samples: Vec<Option<Result<Vec<Duration>, String>>> =
+    parts.iter().map(|_| Some(Ok(vec![]))).collect();
+  for _ in 0..runs {
+    let start = Instant::now();
+    let parsed =
+      catch(|| puzzle.parse(input)).map_err(|error| format!("Invalid input: {}", error))?;
+    parse.push(start.elapsed());
+
+    for (&part, samples) in parts.iter().zip(&mut samples) {
+      if let Some(Ok(durations)) = samples {
+        let start = Instant::now();
+        match solve_part(&*parsed, part) {
+          Ok(Some(_)) => durations.push(start.elapsed()),
+          Ok(None) => *samples = None,
+          Err(error) => *samples = Some(Err(error)),
+        }
+      }
+    }
+  }
+
+  Ok(Timing {
+    day: puzzle.day(),
+    parse: Stats::of(parse),
+    parts: parts
+      .iter()
+      .copied()
+      .zip(samples)
+      .filter_map(|(part, samples)| Some((part, samples?.map(Stats::of))))
+      .collect(),
+  })
+}
+
+/// Lines up `rows` under `header`, with the first `labels` columns left
+/// aligned and the rest right aligned.
+fn table<const N: usize>(header: [&str; N], mut rows: Vec<[String; N]>, labels: usize) -> String {
+  rows.insert(0, header.map(String::from));
+  let mut widths = [0; N];
+  for row in &rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.len());
+    }
+  }
+  rows
+    .iter()
+    .map(|row| {
+      let cells: Vec<String> = row
+        .iter()
+        .zip(&widths)
+        .enumerate()
+        .map(|(column, (cell, &width))| match column < labels {
+          true => format!("{:<width$}", cell, width = width),
+          false => format!("{:>width$}", cell, width = width),
+        })
+        .collect();
+      cells.join("  ").trim_end().to_string()
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Formats `timings` as a table with a row for each day's parse and parts.
+pub fn timing_table(timings: &[Timing]) -> String {
+  let mut rows = Vec::new();
+  let stats_row = |day: u32, step: &str, stats: &Stats| {
+    [
+      format!("{:02}", day),
+      step.to_string(),
+      format!("{:.2?}", stats.min),
+      format!("{:.2?}", stats.median),
+      format!("{:.2?}", stats.max),
+    ]
+  };
+  for timing in timings {
+    rows.push(stats_row(timing.day, "parse", &timing.parse));
+    for (part, stats) in &timing.parts {
+      let step = format!("part {}", part);
+      rows.push(match stats {
+        Ok(stats) => stats_row(timing.day, &step, stats),
+        Err(_) => [
+          format!("{:02}", timing.day),
+          step,
+          "failed".to_string(),
+          String::new(),
+          String::new(),
+        ],
+      });
+    }
+  }
+  table(["Day", "Step", "Min", "Median", "Max"], rows, 2)
+}
+
+/// A way [`time_engines`] runs an Intcode program.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Engine {
+  /// The interpreter, decoding every instruction as it goes.
+  Uncached,
+  /// The interpreter with its decode cache.
+  Cached,
+  /// The program compiled to closures, compiling included.
+  Compiled,
+}
+
+impl Engine {
+  /// Every engine, slowest first.
+  pub const ALL: [Engine; 3] = [Engine::Uncached, Engine::Cached, Engine::Compiled];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Engine::Uncached => "uncached",
+      Engine::Cached => "cached",
+      Engine::Compiled => "compiled",
+    }
+  }
+
+  /// Runs `program` to completion with `input`, returning its output.
+  fn run(self, program: &[ComputerWord], input: i64) -> Result<Vec<ComputerWord>, ComputerError> {
+    let program = program.to_vec();
+    match self {
+      Engine::Uncached => Computer::new(program)
+        .without_decode_cache()
+        .add_input(input)
+        .run(),
+      Engine::Cached => Computer::new(program).add_input(input).run(),
+      Engine::Compiled => {
+        let compiled = Compiled::new(program);
+        compiled.run(compiled.computer().add_input(input))
+      }
+    }
+  }
+}
+
+/// How long one way of running an Intcode program took.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EngineTiming {
+  pub engine: Engine,
+  pub stats: Stats,
+}
+
+/// Runs `program` on `input` `runs` times with each [`Engine`].
+///
+/// Fails if any run fails or if the engines disagree on the output.
+pub fn time_engines(
+  program: &[ComputerWord],
+  input: i64,
+  runs: usize,
+) -> Result<Vec<EngineTiming>, String> {
+  assert!(runs > 0, "Timing needs at least one run");
+
+  let mut expected = None;
+  let mut timings = Vec::new();
+  for engine in Engine::ALL.iter().copied() {
+    let mut samples = Vec::with_capacity(runs);
+    for _ in 0..runs {
+      let start = Instant::now();
+      let output = engine
+        .run(program, input)
+        .map_err(|error| format!("{}: {}", engine.name(), error))?;
+      samples.push(start.elapsed());
+      match &expected {
+        None => expected = Some(output),
+        Some(expected) if *expected != output => {
+          return Err(format!(
+            "{}: output differs from {}",
+            engine.name(),
+            Engine::ALL[0].name()
+          ))
+        }
+        Some(_) => {}
+      }
+    }
+    timings.push(EngineTiming {
+      engine,
+      stats: Stats::of(samples),
+    });
+  }
+  Ok(timings)
+}
+
+/// Formats `timings` as a table, with each engine's speedup over the first.
+pub fn engine_table(timings: &[EngineTiming]) -> String {
+  let baseline = timings.first().map(|timing| timing.stats.median);
+  let rows = timings
+    .iter()
+    .map(|timing| {
+      let speedup = match baseline {
+        Some(baseline) if !timing.stats.median.is_zero() => format!(
+          "{:.1}x",
+          baseline.as_secs_f64() / timing.stats.median.as_secs_f64()
+        ),
+        _ => String::new(),
+      };
+      [
+        timing.engine.name().to_string(),
+        format!("{:.2?}", timing.stats.min),
+        format!("{:.2?}", timing.stats.median),
+        format!("{:.2?}", timing.stats.max),
+        speedup,
+      ]
+    })
+    .collect();
+  table(["Engine", "Min", "Median", "Max", "Speedup"], rows, 1)
+}
+
+fn json_string(value: &str) -> String {
+  let mut json = String::from("\"");
+  for c in value.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+      c => json.push(c),
+    }
+  }
+  json.push('"');
+  json
+}
+
+fn json_stats(stats: &Stats) -> String {
+  format!(
+    "\"min_ns\":{},\"median_ns\":{},\"max_ns\":{}",
+    stats.min.as_nanos(),
+    stats.median.as_nanos(),
+    stats.max.as_nanos()
+  )
+}
+
+impl Timing {
+  /// A single line of JSON, with durations in nanoseconds.
+  pub fn to_json(&self) -> String {
+    let parts: Vec<String> = self
+      .parts
+      .iter()
+      .map(|(part, stats)| match stats {
+        Ok(stats) => format!("{{\"part\":{},{}}}", part, json_stats(stats)),
+        Err(error) => format!("{{\"part\":{},\"error\":{}}}", part, json_string(error)),
+      })
+      .collect();
+    format!(
+      "{{\"day\":{},\"parse\":{{{}}},\"parts\":[{}]}}",
+      self.day,
+      json_stats(&self.parse),
+      parts.join(",")
+    )
+  }
+}
+
+impl EngineTiming {
+  /// A single line of JSON, with durations in nanoseconds.
+  pub fn to_json(&self) -> String {
+    format!(
+      "{{\"engine\":{},{}}}",
+      json_string(self.engine.name()),
+      json_stats(&self.stats)
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{computer, days};
+
+  #[test]
+  fn test_solve() {
+    let day01 = days::find(1).unwrap();
+    assert_eq!(
+      solve(day01, "12\n1969", &[2, 3]),
+      Ok(vec![
+        PartResult {
+          part: 2,
+          answer: Ok(Some("968".to_string()))
+        },
+        PartResult {
+          part: 3,
+          answer: Err("There is no part 3".to_string())
+        },
+      ])
+    );
+    assert_eq!(
+      solve(day01, "twelve", &[1]),
+      Err("Invalid input: Invalid module mass 'twelve'".to_string())
+    );
+  }
+
+  #[test]
+  fn test_panics_become_errors() {
+    // Day 06 panics when there's no path between the two orbits.
+    let day06 = days::find(6).unwrap();
+    let results = solve(day06, "COM)B\nB)YOU\nX)SAN", &[2]).unwrap();
+    assert!(results[0].answer.is_err());
+  }
+
+  #[test]
+  fn test_unsolved_parts() {
+    let day10 = days::find(10).unwrap();
+    let results = solve(day10, "#.\n.#", &[2]).unwrap();
+    assert_eq!(results[0].answer, Ok(None));
+  }
+
+  #[test]
+  fn test_in_parallel_reports_in_order() {
+    let items: Vec<u64> = (0..20).collect();
+    let mut reported = vec![];
+    in_parallel(
+      &items,
+      4,
+      |&item| {
+        // Make the early items finish last.
+        thread::sleep(Duration::from_millis(20 - item));
+        item * 2
+      },
+      |&item, doubled| reported.push((item, doubled)),
+    );
+    assert_eq!(
+      reported,
+      items
+        .iter()
+        .map(|&item| (item, item * 2))
+        .collect::<Vec<_>>()
+    );
+
+    let mut count = 0;
+    in_parallel(&[] as &[u64], 4, |&item| item, |_, _| count += 1);
+    assert_eq!(count, 0);
+  }
+
+  #[test]
+  fn test_stats() {
+    let ms = Duration::from_millis;
+    let stats = Stats::of(vec![ms(5), ms(1), ms(9), ms(2), ms(3)]);
+    assert_eq!(
+      stats,
+      Stats {
+        min: ms(1),
+        median: ms(3),
+        max: ms(9)
+      }
+    );
+  }
+
+  #[test]
+  fn test_time() {
+    let day01 = days::find(1).unwrap();
+    let timing = time(day01, "12\n1969", &[1, 3], 3).unwrap();
+    assert_eq!(timing.day, 1);
+    assert!(timing.parse.min <= timing.parse.median && timing.parse.median <= timing.parse.max);
+    assert!(timing.parts[0].1.is_ok());
+    assert_eq!(timing.parts[1], (3, Err("There is no part 3".to_string())));
+
+    let table = timing_table(std::slice::from_ref(&timing));
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("Day  Step"));
+    assert!(lines[3].starts_with("01   part 3") && lines[3].ends_with("failed"));
+
+    let json = timing.to_json();
+    assert!(json.starts_with("{\"day\":1,\"parse\":{\"min_ns\":"));
+    assert!(json.ends_with("{\"part\":3,\"error\":\"There is no part 3\"}]}"));
+
+    let day10 = days::find(10).unwrap();
+    assert_eq!(time(day10, "#.\n.#", &[2], 1).unwrap().parts, vec![]);
+  }
+
+  #[test]
+  fn test_time_engines() {
+    // Counts down from the input, outputting each number.
+    let program = computer::parse_op_stack("3,12,4,12,1001,12,-1,12,1005,12,2,99,0").unwrap();
+    let timings = time_engines(&program, 3, 2).unwrap();
+    let engines: Vec<Engine> = timings.iter().map(|timing| timing.engine).collect();
+    assert_eq!(engines, Engine::ALL);
+
+    let table = engine_table(&timings);
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("Engine") && lines[0].ends_with("Speedup"));
+    assert!(lines[1].starts_with("uncached") && lines[1].ends_with("1.0x"));
+    assert!(timings[2]
+      .to_json()
+      .starts_with("{\"engine\":\"compiled\",\"min_ns\":"));
+
+    let reads_twice = computer::parse_op_stack("3,0,3,0,99").unwrap();
+    assert_eq!(
+      time_engines(&reads_twice, 1, 1),
+      Err("uncached: No Input @2".to_string())
+    );
+  }
+
+  #[test]
+  fn test_far_jumps() {
+    // Writes a halt far out in memory and jumps to it.
+    let program = computer::parse_op_stack("1101,99,0,1000000000000,1105,1,1000000000000").unwrap();
+    for &engine in Engine::ALL.iter() {
+      assert_eq!(engine.run(&program, 0), Ok(vec![]), "{}", engine.name());
+    }
+  }
+
+  #[test]
+  fn test_json_string() {
+    assert_eq!(json_string("a \"b\"\n\\"), "\"a \\\"b\\\"\\n\\\\\"");
+  }
+}
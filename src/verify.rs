@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::days::{Puzzle, Unsolved};
+use crate::runner;
+
+/// The answers checked in alongside the puzzle inputs.
+pub const ANSWERS: &str = include_str!("days/resources/answers.toml");
+
+/// Expected answers, keyed by day and part.
+///
+/// Read from a small subset of TOML: one `[dayNN]` table per day holding
+/// `part1` and `part2` keys, whose values are integers or basic strings.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Answers(BTreeMap<(u32, usize), String>);
+
+fn unescape(value: &str) -> Result<String, String> {
+  let mut unescaped = String::new();
+  let mut chars = value.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some('n') => unescaped.push('\n'),
+        Some('t') => unescaped.push('\t'),
+        Some('"') => unescaped.push('"'),
+        Some('\\') => unescaped.push('\\'),
+        Some(other) => return Err(format!("Unsupported escape '\\{}'", other)),
+        None => return Err("Unterminated string".to_string()),
+      },
+      '"' => return Err("Unescaped '\"' in string".to_string()),
+      c => unescaped.push(c),
+    }
+  }
+  Ok(unescaped)
+}
+
+fn parse_value(value: &str) -> Result<String, String> {
+  if let Some(string) = value.strip_prefix('"') {
+    let string = string.strip_suffix('"').ok_or("Unterminated string")?;
+    unescape(string)
+  } else if value.parse::<i128>().is_ok() {
+    Ok(value.to_string())
+  } else {
+    Err(format!("Expected an integer or a string, got '{}'", value))
+  }
+}
+
+impl Answers {
+  pub fn parse(text: &str) -> Result<Answers, String> {
+    let mut answers = BTreeMap::new();
+    let mut day = None;
+    for (number, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let error = |message: String| format!("Invalid answers on line {}: {}", number + 1, message);
+
+      if let Some(table) = line.strip_prefix('[') {
+        let table = table
+          .strip_suffix(']')
+          .ok_or_else(|| error(format!("Unterminated table '{}'", line)))?;
+        day = match table.strip_prefix("day").map(str::parse) {
+          Some(Ok(day)) => Some(day),
+          _ => return Err(error(format!("Expected a [dayNN] table, got '{}'", line))),
+        };
+        continue;
+      }
+
+      let day = day.ok_or_else(|| error("Answers must be inside a [dayNN] table".to_string()))?;
+      let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| error(format!("Expected 'partN = answer', got '{}'", line)))?;
+      let part = match key.trim() {
+        "part1" => 1,
+        "part2" => 2,
+        key => return Err(error(format!("Unknown key '{}'", key))),
+      };
+      let value = parse_value(value.trim()).map_err(error)?;
+      if answers.insert((day, part), value).is_some() {
+        return Err(error(format!(
+          "Day {} part {} is recorded twice",
+          day, part
+        )));
+      }
+    }
+    Ok(Answers(answers))
+  }
+
+  pub fn get(&self, day: u32, part: usize) -> Option<&str> {
+    self.0.get(&(day, part)).map(String::as_str)
+  }
+}
+
+/// How one part compared with its recorded answer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Check {
+  Pass,
+  Fail {
+    expected: String,
+    actual: Result<String, String>,
+  },
+  /// Nothing is recorded for this part yet.
+  Missing {
+    actual: Result<String, String>,
+  },
+  /// The day has no checked-in input to run.
+  NoInput,
+  /// The part isn't solved yet and nothing is recorded for it.
+  Unsolved,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Report {
+  pub day: u32,
+  pub part: usize,
+  pub check: Check,
+}
+
+/// Runs both parts of `puzzle` on its checked-in input and compares them
+/// with `answers`.
+pub fn verify(puzzle: &dyn Puzzle, answers: &Answers) -> Vec<Report> {
+  let day = puzzle.day();
+  let report = |part, check| Report { day, part, check };
+
+  if puzzle.input().trim().is_empty() {
+    return (1..=2).map(|part| report(part, Check::NoInput)).collect();
+  }
+  let results = match runner::solve(puzzle, puzzle.input(), &[1, 2]) {
+    Ok(results) => results,
+    Err(error) => {
+      return (1..=2)
+        .map(|part| {
+          let actual = Err(error.clone());
+          let check = match answers.get(day, part) {
+            Some(expected) => Check::Fail {
+              expected: expected.to_string(),
+              actual,
+            },
+            None => Check::Missing { actual },
+          };
+          report(part, check)
+        })
+        .collect()
+    }
+  };
+
+  results
+    .into_iter()
+    .map(|result| {
+      // An unsolved part only counts against a recorded answer.
+      let check = match (answers.get(day, result.part), result.answer) {
+        (None, Ok(None)) => Check::Unsolved,
+        (expected, actual) => {
+          let actual = actual.and_then(|actual| actual.ok_or_else(|| Unsolved.to_string()));
+          match (expected, actual) {
+            (Some(expected), Ok(ref actual)) if expected == actual => Check::Pass,
+            (Some(expected), actual) => Check::Fail {
+              expected: expected.to_string(),
+              actual,
+            },
+            (None, actual) => Check::Missing { actual },
+          }
+        }
+      };
+      report(result.part, check)
+    })
+    .collect()
+}
+
+impl fmt::Display for Report {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Day {:02} part {}: ", self.day, self.part)?;
+    match &self.check {
+      Check::Pass => write!(f, "pass"),
+      Check::NoInput => write!(f, "skipped, no input"),
+      Check::Unsolved => write!(f, "skipped, not solved yet"),
+      Check::Missing { actual: Ok(actual) } => {
+        write!(f, "missing, got {}", actual.escape_default())
+      }
+      Check::Missing { actual: Err(error) } => write!(f, "missing, and failed: {}", error),
+      Check::Fail {
+        expected,
+        actual: Err(error),
+      } => write!(
+        f,
+        "fail, expected {} but {}",
+        expected.escape_default(),
+        error
+      ),
+      Check::Fail {
+        expected,
+        actual: Ok(actual),
+      } => {
+        write!(f, "fail")?;
+        for line in expected.lines() {
+          write!(f, "\n  - {}", line)?;
+        }
+        for line in actual.lines() {
+          write!(f, "\n  + {}", line)?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::days;
+
+  #[test]
+  fn test_parse() {
+    let answers = Answers::parse(
+      "# Recorded answers\n\n[day01]\npart1 = 3367126\npart2 = \"a \\\"b\\\"\\nc\"\n\n[day10]\npart1 = -4\n",
+    )
+    .unwrap();
+    assert_eq!(answers.get(1, 1), Some("3367126"));
+    assert_eq!(answers.get(1, 2), Some("a \"b\"\nc"));
+    assert_eq!(answers.get(10, 1), Some("-4"));
+    assert_eq!(answers.get(10, 2), None);
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    assert_eq!(
+      Answers::parse("part1 = 1"),
+      Err("Invalid answers on line 1: Answers must be inside a [dayNN] table".to_string())
+    );
+    assert_eq!(
+      Answers::parse("[day01]\npart3 = 1"),
+      Err("Invalid answers on line 2: Unknown key 'part3'".to_string())
+    );
+    assert_eq!(
+      Answers::parse("[day01]\npart1 = 1\npart1 = 2"),
+      Err("Invalid answers on line 3: Day 1 part 1 is recorded twice".to_string())
+    );
+    assert!(Answers::parse("[day01]\npart1 = one").is_err());
+    assert!(Answers::parse("[day01]\npart1 = \"one").is_err());
+    assert!(Answers::parse("[puzzle]").is_err());
+  }
+
+  #[test]
+  fn test_checked_in_answers_parse() {
+    let answers = Answers::parse(ANSWERS).unwrap();
+    assert_eq!(answers.get(2, 1), Some("6627023"));
+  }
+
+  #[test]
+  fn test_verify() {
+    let day01 = days::find(1).unwrap();
+    let answers = Answers::parse("[day01]\npart1 = 3367126\npart2 = 1").unwrap();
+    let reports = verify(day01, &answers);
+    assert_eq!(reports[0].check, Check::Pass);
+    assert_eq!(
+      reports[1].check,
+      Check::Fail {
+        expected: "1".to_string(),
+        actual: Ok("5047796".to_string())
+      }
+    );
+    assert_eq!(
+      reports[1].to_string(),
+      "Day 01 part 2: fail\n  - 1\n  + 5047796"
+    );
+
+    let reports = verify(days::find(7).unwrap(), &answers);
+    assert_eq!(reports[0].check, Check::NoInput);
+
+    let reports = verify(days::find(10).unwrap(), &answers);
+    assert_eq!(reports[1].check, Check::Unsolved);
+    assert_eq!(
+      reports[1].to_string(),
+      "Day 10 part 2: skipped, not solved yet"
+    );
+  }
+}